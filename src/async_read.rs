@@ -0,0 +1,148 @@
+use std::io;
+
+use serde::de::DeserializeOwned;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::{from_bytes, Error, Header, Result};
+
+/// Read a frame consisting of the 4-byte size of the rest of the frame
+/// followed by `T`, same as [`crate::to_bytes`], from `reader`.
+///
+/// Unlike [`crate::SshFormatCodec`], only the bytes of the frame are read,
+/// so the reader can be used for something else afterwards.
+///
+/// The connection being closed or reset before the frame is complete is
+/// reported as [`Error::Eof`].
+pub async fn read_frame<R, T>(reader: &mut R) -> Result<T>
+where
+    R: AsyncRead + Unpin + ?Sized,
+    T: DeserializeOwned,
+{
+    let mut header = [0_u8; 4];
+    reader.read_exact(&mut header).await.map_err(map_io_error)?;
+
+    let len = Header::from_bytes(header).len();
+
+    let mut body = Vec::new();
+    if len != 0 {
+        // Grow the buffer with the bytes actually read, so that a bogus
+        // length does not allocate a huge buffer upfront.
+        reader
+            .take(len.into())
+            .read_to_end(&mut body)
+            .await
+            .map_err(map_io_error)?;
+
+        if body.len() != len as usize {
+            return Err(Error::Eof);
+        }
+    }
+
+    let (t, _trailing_bytes) = from_bytes(&body)?;
+    Ok(t)
+}
+
+fn map_io_error(err: io::Error) -> Error {
+    match err.kind() {
+        io::ErrorKind::ConnectionReset => Error::Eof,
+        _ => err.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use serde::{Deserialize, Serialize};
+    use tokio::io::ReadBuf;
+
+    use super::*;
+    use crate::to_bytes;
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Request {
+        id: u32,
+        path: String,
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_read_frame() {
+        let request = Request {
+            id: 1,
+            path: "/tmp/mux".to_owned(),
+        };
+        let frames = [
+            to_bytes(&request).unwrap(),
+            to_bytes(&()).unwrap(),
+            to_bytes(&2_u8).unwrap(),
+        ]
+        .concat();
+        let mut reader = &frames[..];
+
+        block_on(async {
+            assert_eq!(
+                read_frame::<_, Request>(&mut reader).await.unwrap(),
+                request
+            );
+            read_frame::<_, ()>(&mut reader).await.unwrap();
+            assert_eq!(read_frame::<_, u8>(&mut reader).await.unwrap(), 2);
+        });
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_eof() {
+        let frame = to_bytes(&"Hello").unwrap();
+
+        for len in 0..frame.len() {
+            let mut reader = &frame[..len];
+            assert!(matches!(
+                block_on(read_frame::<_, String>(&mut reader)),
+                Err(Error::Eof)
+            ));
+        }
+
+        // Length larger than the input
+        let mut reader = &[0xff, 0xff, 0xff, 0xff, 0][..];
+        assert!(matches!(
+            block_on(read_frame::<_, String>(&mut reader)),
+            Err(Error::Eof)
+        ));
+    }
+
+    #[test]
+    fn test_io_error() {
+        struct Failing(io::ErrorKind);
+
+        impl AsyncRead for Failing {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+                _buf: &mut ReadBuf<'_>,
+            ) -> Poll<io::Result<()>> {
+                Poll::Ready(Err(self.0.into()))
+            }
+        }
+
+        assert!(matches!(
+            block_on(read_frame::<_, u32>(&mut Failing(
+                io::ErrorKind::ConnectionReset
+            ))),
+            Err(Error::Eof)
+        ));
+        assert!(matches!(
+            block_on(read_frame::<_, u32>(&mut Failing(io::ErrorKind::PermissionDenied))),
+            Err(Error::IoError(err)) if err.kind() == io::ErrorKind::PermissionDenied
+        ));
+    }
+}
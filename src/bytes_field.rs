@@ -0,0 +1,82 @@
+//! Serialize and deserialize [`Bytes`] as length(`u32`) + content.
+//!
+//! Use it with `#[serde(with = "ssh_format::bytes_field")]`.
+//!
+//! The content is copied on deserialization unless it is owned, since
+//! serde only exposes it as a slice. Use
+//! [`crate::Deserializer::read_shared_bytes`] to share the buffer of the
+//! input instead.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use bytes::Bytes;
+use serde::{de, Deserializer, Serializer};
+
+pub fn serialize<S>(bytes: &Bytes, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_bytes(bytes)
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Bytes, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct BytesVisitor;
+
+    impl<'de> de::Visitor<'de> for BytesVisitor {
+        type Value = Bytes;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("bytes")
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<Bytes, E> {
+            Ok(Bytes::copy_from_slice(v))
+        }
+
+        /// Reuse the owned buffer instead of copying it.
+        fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Bytes, E> {
+            Ok(Bytes::from(v))
+        }
+    }
+
+    deserializer.deserialize_byte_buf(BytesVisitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{from_bytes, to_bytes, Deserializer};
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Data {
+        id: u32,
+        #[serde(with = "crate::bytes_field")]
+        payload: Bytes,
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let data = Data {
+            id: 0x1234,
+            payload: Bytes::from_static(b"Hello, world!"),
+        };
+
+        let serialized = to_bytes(&data).unwrap();
+        assert_eq!(serialized[4..8], [0, 0, 0x12, 0x34]);
+        assert_eq!(serialized[8..12], [0, 0, 0, 13]);
+        assert_eq!(&serialized[12..], b"Hello, world!");
+
+        assert_eq!(from_bytes::<Data>(&serialized[4..]).unwrap().0, data);
+
+        // Payload spans multiple slices
+        let body = &serialized[4..];
+        let mut deserializer = Deserializer::new(body.chunks(3).fuse());
+        assert_eq!(Data::deserialize(&mut deserializer).unwrap(), data);
+    }
+}
@@ -0,0 +1,256 @@
+use std::{convert::TryInto, fmt, marker::PhantomData};
+
+use bytes::{Buf, BytesMut};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{from_bytes, Error, Header, Serializer};
+
+/// Maximum number of bytes reserved at once for a partially received
+/// frame, so that a bogus header cannot force a huge allocation.
+const MAX_RESERVE: usize = 8 * 1024;
+
+/// [`Encoder`] and [`Decoder`] of frames consisting of the 4-byte size of
+/// the rest of the frame followed by `T`, same as [`crate::to_bytes`].
+///
+/// Use it with e.g. `tokio_util::codec::Framed` over a `UnixStream`
+/// connected to the mux server.
+pub struct SshFormatCodec<T> {
+    max_frame_len: usize,
+    phantom: PhantomData<fn(T) -> T>,
+}
+
+impl<T> SshFormatCodec<T> {
+    pub const fn new() -> Self {
+        Self {
+            max_frame_len: usize::MAX,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Fail with [`Error::LengthLimitExceeded`] on frames whose body is
+    /// larger than `max_frame_len`, before buffering them.
+    ///
+    /// Unbounded by default.
+    pub const fn max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+}
+
+impl<T> Default for SshFormatCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for SshFormatCodec<T> {
+    fn clone(&self) -> Self {
+        Self {
+            max_frame_len: self.max_frame_len,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for SshFormatCodec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SshFormatCodec")
+            .field("max_frame_len", &self.max_frame_len)
+            .finish()
+    }
+}
+
+impl<T: Serialize> Encoder<T> for SshFormatCodec<T> {
+    type Error = Error;
+
+    /// Nothing is left in `dst` if serialization fails.
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), Error> {
+        let start = dst.len();
+        dst.extend_from_slice(&[0, 0, 0, 0]);
+
+        let mut serializer = Serializer::new(&mut *dst);
+        let result = item
+            .serialize(&mut serializer)
+            .and_then(|()| serializer.create_header(0));
+
+        match result {
+            Ok(header) => {
+                dst[start..start + 4].copy_from_slice(&header);
+                Ok(())
+            }
+            Err(err) => {
+                dst.truncate(start);
+                Err(err)
+            }
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Decoder for SshFormatCodec<T> {
+    type Item = T;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>, Error> {
+        let header: [u8; 4] = match src.get(..4) {
+            Some(header) => header.try_into().unwrap(),
+            None => return Ok(None),
+        };
+        let len: usize = Header::from_bytes(header)
+            .len()
+            .try_into()
+            .map_err(|_| Error::TooLong)?;
+
+        if len > self.max_frame_len {
+            return Err(Error::LengthLimitExceeded {
+                len,
+                max_len: self.max_frame_len,
+            });
+        }
+
+        let frame_len = len.checked_add(4).ok_or(Error::TooLong)?;
+        if src.len() < frame_len {
+            src.reserve((frame_len - src.len()).min(MAX_RESERVE));
+            return Ok(None);
+        }
+
+        src.advance(4);
+        let body = src.split_to(len);
+
+        let (t, _trailing_bytes) = from_bytes(&body)?;
+        Ok(Some(t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::to_bytes;
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Request {
+        id: u32,
+        path: String,
+    }
+
+    fn request(id: u32) -> Request {
+        Request {
+            id,
+            path: "/tmp/mux".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut codec = SshFormatCodec::new();
+        let mut dst = BytesMut::new();
+
+        codec.encode(request(1), &mut dst).unwrap();
+        codec.encode(request(2), &mut dst).unwrap();
+
+        let expected = [
+            to_bytes(&request(1)).unwrap(),
+            to_bytes(&request(2)).unwrap(),
+        ]
+        .concat();
+        assert_eq!(dst, expected);
+    }
+
+    #[test]
+    fn test_encode_error() {
+        struct Invalid;
+
+        impl Serialize for Invalid {
+            fn serialize<S: serde::Serializer>(
+                &self,
+                _serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                Err(serde::ser::Error::custom("invalid"))
+            }
+        }
+
+        let mut dst = BytesMut::from(&b"abc"[..]);
+        assert!(matches!(
+            SshFormatCodec::new().encode(Invalid, &mut dst),
+            Err(Error::Message(_))
+        ));
+        assert_eq!(dst, &b"abc"[..]);
+    }
+
+    #[test]
+    fn test_decode_partial() {
+        let mut codec = SshFormatCodec::<Request>::new();
+        let frames = [
+            to_bytes(&request(1)).unwrap(),
+            to_bytes(&request(2)).unwrap(),
+        ]
+        .concat();
+
+        let mut src = BytesMut::new();
+        let mut decoded = Vec::new();
+
+        // Feed one byte at a time
+        for byte in frames {
+            src.extend_from_slice(&[byte]);
+            if let Some(request) = codec.decode(&mut src).unwrap() {
+                decoded.push(request);
+            }
+        }
+
+        assert_eq!(decoded, [request(1), request(2)]);
+        assert!(src.is_empty());
+        assert!(codec.decode(&mut src).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_decode_multiple() {
+        let mut codec = SshFormatCodec::<Request>::new();
+        let frame = to_bytes(&request(1)).unwrap();
+
+        let mut src = BytesMut::from(&[&frame[..], &frame[..], &frame[..3]].concat()[..]);
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(request(1)));
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(request(1)));
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        assert_eq!(src, frame[..3]);
+    }
+
+    #[test]
+    fn test_max_frame_len() {
+        let frame = to_bytes(&request(1)).unwrap();
+        let len = frame.len() - 4;
+
+        let mut codec = SshFormatCodec::<Request>::new().max_frame_len(len);
+        assert_eq!(
+            codec.decode(&mut BytesMut::from(&frame[..])).unwrap(),
+            Some(request(1))
+        );
+
+        // Rejected as soon as the header is received
+        let mut codec = SshFormatCodec::<Request>::new().max_frame_len(len - 1);
+        assert!(matches!(
+            codec.decode(&mut BytesMut::from(&frame[..4])),
+            Err(Error::LengthLimitExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn test_decode_bogus_header() {
+        let mut codec = SshFormatCodec::<Request>::new();
+        let mut src = BytesMut::from(&[0xff, 0xff, 0xff, 0xff, 0][..]);
+
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        assert!(src.capacity() <= 5 + MAX_RESERVE);
+    }
+
+    #[test]
+    fn test_decode_invalid_body() {
+        // The body is too short for `Request`
+        let mut src = BytesMut::from(&[0, 0, 0, 2, 0, 1][..]);
+        assert!(matches!(
+            SshFormatCodec::<Request>::new().decode(&mut src),
+            Err(Error::Eof)
+        ));
+    }
+}
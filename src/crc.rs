@@ -0,0 +1,163 @@
+use alloc::{vec, vec::Vec};
+use core::convert::TryInto;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{from_bytes, split_header, Error, Result, Serializer};
+
+/// Lookup table of CRC-32 (IEEE 802.3), using the reversed polynomial.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0_u32; 256];
+
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+};
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, byte| {
+        CRC32_TABLE[((crc ^ u32::from(*byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Return a byte array of `[len][body][crc32(body)]`, where `len` is the
+/// size of the body plus the 4-byte CRC-32.
+pub fn to_bytes_crc<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize,
+{
+    let mut buffer = vec![0, 0, 0, 0];
+
+    let mut serializer = Serializer::new(&mut buffer);
+    value.serialize(&mut serializer)?;
+    let header = serializer.create_header(4)?;
+
+    let crc = crc32(&buffer[4..]);
+    buffer.extend_from_slice(&crc.to_be_bytes());
+
+    buffer[..4].copy_from_slice(&header);
+
+    Ok(buffer)
+}
+
+/// Return a deserialized value and the bytes following its frame.
+///
+/// `s` must start with a frame written by [`to_bytes_crc`], the CRC-32
+/// is verified before deserializing the body and
+/// [`Error::ChecksumMismatch`] is returned on mismatch.
+///
+/// `T` must consume the whole body, otherwise [`Error::InvalidLength`] is
+/// returned.
+pub fn from_bytes_crc<'a, T>(s: &'a [u8]) -> Result<(T, &'a [u8])>
+where
+    T: Deserialize<'a>,
+{
+    let (header, s) = split_header(s)?;
+    let len: usize = header.len().try_into().map_err(|_| Error::TooLong)?;
+
+    if len < 4 || s.len() < len {
+        return Err(Error::Eof);
+    }
+    let (frame, rest) = s.split_at(len);
+    let (body, crc) = frame.split_at(len - 4);
+
+    let (expected, _) = from_bytes::<u32>(crc)?;
+    let computed = crc32(body);
+    if expected != computed {
+        return Err(Error::ChecksumMismatch { expected, computed });
+    }
+
+    let (t, trailing) = from_bytes(body)?;
+    if !trailing.is_empty() {
+        return Err(Error::InvalidLength {
+            expected: body.len(),
+            found: body.len() - trailing.len(),
+        });
+    }
+
+    Ok((t, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+    use crate::to_bytes;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let value = (0x1234_u16, "Hello, world!");
+
+        let mut serialized = to_bytes_crc(&value).unwrap();
+        let plain = to_bytes(&value).unwrap();
+
+        assert_eq!(serialized[..4], (plain.len() as u32).to_be_bytes());
+        assert_eq!(serialized[4..plain.len()], plain[4..]);
+        assert_eq!(serialized[plain.len()..], crc32(&plain[4..]).to_be_bytes());
+
+        serialized.extend_from_slice(&to_bytes_crc(&0x12345678_u32).unwrap());
+
+        let (deserialized, rest) = from_bytes_crc::<(u16, &str)>(&serialized).unwrap();
+        assert_eq!(deserialized, value);
+
+        let (deserialized, rest) = from_bytes_crc::<u32>(rest).unwrap();
+        assert_eq!(deserialized, 0x12345678);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_corruption() {
+        let mut serialized = to_bytes_crc(&(0x1234_u16, "Hello, world!")).unwrap();
+        let computed = crc32(&serialized[4..serialized.len() - 4]);
+
+        serialized[6] ^= 1;
+
+        assert_matches!(
+            from_bytes_crc::<(u16, &str)>(&serialized),
+            Err(Error::ChecksumMismatch { expected, computed: actual })
+                if expected == computed && actual != computed
+        );
+
+        // Truncated frame
+        assert_matches!(
+            from_bytes_crc::<(u16, &str)>(&serialized[..serialized.len() - 1]),
+            Err(Error::Eof)
+        );
+    }
+
+    #[test]
+    fn test_trailing_bytes() {
+        let serialized = to_bytes_crc(&(0x1234_u16, "Hello, world!")).unwrap();
+
+        assert_matches!(
+            from_bytes_crc::<u16>(&serialized),
+            Err(Error::InvalidLength {
+                expected: 19,
+                found: 2
+            })
+        );
+    }
+}
@@ -1,19 +1,137 @@
-use std::{borrow::Cow, convert::TryInto, iter, str};
-
-use serde::de::{self, DeserializeSeed, IntoDeserializer, SeqAccess, VariantAccess, Visitor};
+use alloc::{borrow::Cow, string::String, vec};
+use core::{
+    convert::TryInto,
+    iter, mem,
+    net::{IpAddr, SocketAddr},
+    slice, str,
+};
+#[cfg(feature = "std")]
+use std::io;
+
+#[cfg(feature = "bytes")]
+use bytes::Bytes;
+
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, IntoDeserializer, SeqAccess, VariantAccess, Visitor,
+};
 use serde::Deserialize;
 
-use crate::{Error, Result};
+use crate::{split_header, Endianness, Error, Result};
 
 #[derive(Copy, Clone, Debug)]
 pub struct Deserializer<'de, It> {
-    slice: &'de [u8],
+    pub(crate) slice: &'de [u8],
     iter: It,
+    lossy_strings: bool,
+    lenient_bool: bool,
+    max_len: usize,
+    endianness: Endianness,
+    pos: usize,
+    /// Nesting level of the value being deserialized, 0 for top-level.
+    depth: usize,
+    field_count: u32,
+    /// Chunks the slices come from, see [`Deserializer::from_bytes_chunks`].
+    #[cfg(feature = "bytes")]
+    chunks: &'de [Bytes],
 }
 
 impl<'de, It> Deserializer<'de, It> {
     pub const fn new(iter: It) -> Self {
-        Self { iter, slice: &[] }
+        Self {
+            iter,
+            slice: &[],
+            lossy_strings: false,
+            lenient_bool: false,
+            max_len: usize::MAX,
+            endianness: Endianness::Big,
+            pos: 0,
+            depth: 0,
+            field_count: 0,
+            #[cfg(feature = "bytes")]
+            chunks: &[],
+        }
+    }
+
+    /// If enabled, strings that are not valid UTF-8 are decoded lossily
+    /// using [`String::from_utf8_lossy`] instead of failing with
+    /// [`Error::InvalidStr`].
+    ///
+    /// Since the replaced string cannot be borrowed from the input, it
+    /// can only be deserialized into owned strings, e.g. `String`.
+    ///
+    /// Disabled by default.
+    pub const fn lossy_strings(mut self, lossy_strings: bool) -> Self {
+        self.lossy_strings = lossy_strings;
+        self
+    }
+
+    /// If enabled, any nonzero `u32` is decoded as `true`, like C code
+    /// treating it as a boolean, instead of failing with
+    /// [`Error::InvalidBoolEncoding`] on anything other than 0 and 1.
+    ///
+    /// Disabled by default.
+    pub const fn lenient_bool(mut self, lenient_bool: bool) -> Self {
+        self.lenient_bool = lenient_bool;
+        self
+    }
+
+    /// Fail with [`Error::LengthLimitExceeded`] on strings and bytes
+    /// declaring a length larger than `max_len`, before anything is
+    /// allocated for them.
+    ///
+    /// This protects against a peer declaring a huge length, e.g.
+    /// `u32::MAX`, to force a large allocation.
+    ///
+    /// Unbounded by default.
+    pub const fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Set the byte order of integers, including lengths, which is big
+    /// endian by default as required by the openssh mux protocol.
+    pub const fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Return the variant index of the enum about to be deserialized
+    /// without consuming it, e.g. to dispatch unknown variants before
+    /// calling `deserialize_enum`.
+    ///
+    /// Only the current slice is looked at, so `None` is returned if it
+    /// has fewer than 4 bytes, which never happens for contiguous input
+    /// holding the index.
+    pub fn peek_variant_index(&self) -> Option<u32> {
+        let bytes = self.slice.get(..4)?.try_into().unwrap();
+        Some(match self.endianness {
+            Endianness::Big => u32::from_be_bytes(bytes),
+            Endianness::Little => u32::from_le_bytes(bytes),
+        })
+    }
+
+    /// Return the number of bytes consumed so far, e.g. to locate where
+    /// a malformed message failed to deserialize.
+    ///
+    /// On error, it points past the bytes read for the failing value.
+    pub const fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Return the number of values deserialized at the top level so far,
+    /// counted the same way as by
+    /// [`crate::Serializer::enable_field_count_trailer`].
+    pub const fn field_count(&self) -> u32 {
+        self.field_count
+    }
+
+    /// Return the unparsed bytes of the current slice, without pulling
+    /// the next one from the iterator.
+    ///
+    /// It may be empty even if more input is available, use
+    /// [`Deserializer::is_at_end`] to check for that.
+    pub fn remaining_slice(&self) -> &'de [u8] {
+        self.slice
     }
 
     pub fn into_inner(self) -> (&'de [u8], It) {
@@ -25,7 +143,44 @@ impl<'de> Deserializer<'de, iter::Empty<&'de [u8]>> {
     pub const fn from_bytes(slice: &'de [u8]) -> Self {
         Self {
             slice,
-            iter: iter::empty(),
+            ..Self::new(iter::empty())
+        }
+    }
+
+    /// Point the deserializer at `slice`, e.g. to reuse it for the next
+    /// message, discarding any data left and resetting
+    /// [`Self::position`], while keeping its settings.
+    pub fn reset(&mut self, slice: &'de [u8]) {
+        self.slice = slice;
+        self.pos = 0;
+        self.depth = 0;
+        self.field_count = 0;
+    }
+}
+
+impl<'de> Deserializer<'de, iter::Copied<slice::Iter<'de, &'de [u8]>>> {
+    /// Deserialize from scatter-gather buffers, e.g. chunks received
+    /// via `readv`.
+    ///
+    /// Empty slices are skipped. Strings and bytes lying within a single
+    /// slice are borrowed from it without copying.
+    pub fn from_slices(slices: &'de [&'de [u8]]) -> Self {
+        Self::new(slices.iter().copied())
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<'de> Deserializer<'de, iter::Map<slice::Iter<'de, Bytes>, fn(&'de Bytes) -> &'de [u8]>> {
+    /// Same as [`Deserializer::from_slices`], but from [`Bytes`] chunks,
+    /// e.g. received by a `tokio_util` codec, or a single one with
+    /// [`slice::from_ref`].
+    ///
+    /// Fields read by [`Deserializer::read_shared_bytes`] then share the
+    /// buffer of their chunk instead of being copied.
+    pub fn from_bytes_chunks(chunks: &'de [Bytes]) -> Self {
+        Self {
+            chunks,
+            ..Self::new(chunks.iter().map(|chunk| &chunk[..]))
         }
     }
 }
@@ -39,7 +194,8 @@ impl<'de> Deserializer<'de, iter::Empty<&'de [u8]>> {
 /// ```ignore
 /// let serialized = to_bytes(value).unwrap();
 /// // Ignore the size
-/// let (new_value, _trailing_bytes) = from_bytes::<T>(&serialized[4..]).unwrap();
+/// let (_header, body) = split_header(&serialized).unwrap();
+/// let (new_value, _trailing_bytes) = from_bytes::<T>(body).unwrap();
 ///
 /// assert_eq!(value, new_value);
 /// ```
@@ -66,51 +222,122 @@ where
     Ok((t, deserializer.slice))
 }
 
-impl<'de, It> Deserializer<'de, It>
+/// Same as [`from_bytes`], but return the number of bytes consumed
+/// instead of the trailing bytes, e.g. to advance a cursor.
+pub fn from_bytes_consumed<'a, T>(s: &'a [u8]) -> Result<(T, usize)>
 where
-    It: iter::FusedIterator + Iterator<Item = &'de [u8]>,
+    T: Deserialize<'a>,
 {
-    /// Extract the loop as a separate function so that `Self::update_slice`
-    /// can be trivally inlined.
-    fn update_slice_inner(&mut self) {
-        self.slice = self.iter.find(|slice| !slice.is_empty()).unwrap_or(&[]);
-    }
+    let mut deserializer = Deserializer::from_bytes(s);
+    let t = T::deserialize(&mut deserializer)?;
+    Ok((t, deserializer.position()))
+}
 
-    #[inline]
-    fn update_slice(&mut self) {
-        if self.slice.is_empty() {
-            self.update_slice_inner();
-        }
+/// Return a deserialized value and the bytes following its frame.
+///
+/// Unlike [`from_bytes`], `s` must start with the 4-byte size written by
+/// [`crate::to_bytes`] and `T` is deserialized from exactly that many
+/// following bytes, so the returned bytes can be used to deserialize the
+/// next frame.
+pub fn from_bytes_framed<'a, T>(s: &'a [u8]) -> Result<(T, &'a [u8])>
+where
+    T: Deserialize<'a>,
+{
+    let (header, s) = split_header(s)?;
+    let len: usize = header.len().try_into().map_err(|_| Error::TooLong)?;
+
+    if s.len() < len {
+        return Err(Error::Eof);
     }
+    let (frame, rest) = s.split_at(len);
 
-    fn next_byte(&mut self) -> Result<u8> {
-        self.update_slice();
+    let (t, _trailing_bytes) = from_bytes(frame)?;
+    Ok((t, rest))
+}
 
-        let byte = self.slice.first().copied().ok_or(Error::Eof)?;
-        self.slice = &self.slice[1..];
+/// Return a deserialized value of a message carrying its own body length.
+///
+/// `s` must be exactly one message written by [`crate::to_bytes`]: its
+/// leading `u32` length is checked against the number of bytes following
+/// it, then `T` is deserialized from them. On mismatch,
+/// [`Error::InvalidLength`] is returned.
+pub fn from_bytes_self_len<'a, T>(s: &'a [u8]) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let (header, body) = split_header(s)?;
+    let expected: usize = header.len().try_into().map_err(|_| Error::TooLong)?;
 
-        Ok(byte)
+    if expected != body.len() {
+        return Err(Error::InvalidLength {
+            expected,
+            found: body.len(),
+        });
     }
 
-    fn fill_buffer(&mut self, mut buffer: &mut [u8]) -> Result<()> {
-        loop {
-            if buffer.is_empty() {
-                break Ok(());
-            }
+    let (t, _trailing_bytes) = from_bytes(body)?;
+    Ok(t)
+}
 
-            self.update_slice();
+/// Return an iterator deserializing each frame of `buf`, which consists
+/// of concatenated frames written by [`crate::to_bytes`].
+///
+/// It stops after yielding an error, e.g. [`Error::Eof`] on a truncated
+/// frame.
+pub fn decode_all<'a, T>(buf: &'a [u8]) -> impl Iterator<Item = Result<T>> + 'a
+where
+    T: Deserialize<'a>,
+{
+    let mut rest = Some(buf);
 
-            if self.slice.is_empty() {
-                break Err(Error::Eof);
-            }
+    iter::from_fn(move || {
+        let buf = rest.take().filter(|buf| !buf.is_empty())?;
 
-            let n = self.slice.len().min(buffer.len());
+        Some(from_bytes_framed(buf).map(|(t, next)| {
+            rest = Some(next);
+            t
+        }))
+    })
+}
 
-            buffer[..n].copy_from_slice(&self.slice[..n]);
+/// Source of the bytes to deserialize, shared by [`Deserializer`] and
+/// [`crate::ReaderDeserializer`].
+pub(crate) trait Input<'de> {
+    fn next_byte(&mut self) -> Result<u8>;
 
-            self.slice = &self.slice[n..];
-            buffer = &mut buffer[n..];
-        }
+    fn fill_buffer(&mut self, buffer: &mut [u8]) -> Result<()>;
+
+    /// Return the next `size` bytes, borrowed from the input if possible.
+    fn next_bytes(&mut self, size: usize) -> Result<Cow<'de, [u8]>>;
+
+    fn has_remaining_input(&mut self) -> Result<bool>;
+
+    /// Consume all remaining data, borrowed from the input if possible.
+    fn take_rest(&mut self) -> Result<Cow<'de, [u8]>>;
+
+    /// Maximum length of strings and bytes.
+    fn max_len(&self) -> usize;
+
+    fn endianness(&self) -> Endianness;
+
+    /// Enter a value, counted as a field if it is at the top level.
+    ///
+    /// Fields are only counted by [`Deserializer`].
+    fn begin_field(&mut self) {}
+
+    fn end_field(&mut self) {}
+
+    /// Deserialize a value with `f`, counted as a field if it is at the
+    /// top level.
+    fn counted<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        self.begin_field();
+        let result = f(self);
+        self.end_field();
+
+        result
     }
 
     /// * `SIZE` - must not be 0!
@@ -124,317 +351,679 @@ where
     }
 
     fn next_u32(&mut self) -> Result<u32> {
-        Ok(u32::from_be_bytes(self.next_bytes_const()?))
-    }
-
-    fn next_bytes(&mut self, size: usize) -> Result<Cow<'de, [u8]>> {
-        self.update_slice();
-
-        if self.slice.len() >= size {
-            let slice = &self.slice[..size];
-            self.slice = &self.slice[size..];
-
-            Ok(Cow::Borrowed(slice))
-        } else {
-            let mut bytes = vec![0_u8; size];
-            self.fill_buffer(&mut bytes)?;
-            Ok(Cow::Owned(bytes))
-        }
+        let bytes = self.next_bytes_const()?;
+        Ok(match self.endianness() {
+            Endianness::Big => u32::from_be_bytes(bytes),
+            Endianness::Little => u32::from_le_bytes(bytes),
+        })
     }
 
     /// Parse &str and &[u8]
     fn parse_bytes(&mut self) -> Result<Cow<'de, [u8]>> {
         let len: usize = self.next_u32()?.try_into().map_err(|_| Error::TooLong)?;
-        self.next_bytes(len)
-    }
-
-    /// Is there any remaining data.
-    pub fn has_remaining_data(&mut self) -> bool {
-        self.update_slice();
-        !self.slice.is_empty()
-    }
-}
 
-macro_rules! impl_for_deserialize_primitive {
-    ( $name:ident, $visitor_fname:ident, $type:ty ) => {
-        fn $name<V>(self, visitor: V) -> Result<V::Value>
-        where
-            V: Visitor<'de>,
-        {
-            visitor.$visitor_fname(<$type>::from_be_bytes(self.next_bytes_const()?))
+        let max_len = self.max_len();
+        if len > max_len {
+            return Err(Error::LengthLimitExceeded { len, max_len });
         }
-    };
+
+        self.next_bytes(len)
+    }
 }
 
-impl<'de, 'a, It> de::Deserializer<'de> for &'a mut Deserializer<'de, It>
+impl<'de, It> Deserializer<'de, It>
 where
     It: iter::FusedIterator + Iterator<Item = &'de [u8]>,
 {
-    type Error = Error;
+    /// Extract the loop as a separate function so that `Self::update_slice`
+    /// can be trivally inlined.
+    fn update_slice_inner(&mut self) {
+        self.slice = self.iter.find(|slice| !slice.is_empty()).unwrap_or(&[]);
+    }
 
-    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        match self.next_u32()? {
-            1 => visitor.visit_bool(true),
-            0 => visitor.visit_bool(false),
-            _ => Err(Error::InvalidBoolEncoding),
+    #[inline]
+    fn update_slice(&mut self) {
+        if self.slice.is_empty() {
+            self.update_slice_inner();
         }
     }
 
-    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        visitor.visit_u8(self.next_byte()?)
+    /// Consume the first `n` bytes of the current slice.
+    fn advance(&mut self, n: usize) {
+        self.slice = &self.slice[n..];
+        self.pos += n;
     }
 
-    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
+    /// Read a length-prefixed bytes field, passing its content to `f` in
+    /// chunks as they are found in the input instead of collecting it,
+    /// e.g. for hashing a large payload.
+    ///
+    /// Each chunk is borrowed from a slice of the input, so the field is
+    /// never copied. The error returned by `f`, if any, is returned
+    /// immediately and the rest of the field is left unread.
+    pub fn read_bytes_streaming<F>(&mut self, mut f: F) -> Result<()>
     where
-        V: Visitor<'de>,
+        F: FnMut(&[u8]) -> Result<()>,
     {
-        visitor.visit_i8(self.next_byte()? as i8)
-    }
+        let mut len: usize = self.next_u32()?.try_into().map_err(|_| Error::TooLong)?;
 
-    impl_for_deserialize_primitive!(deserialize_i16, visit_i16, i16);
-    impl_for_deserialize_primitive!(deserialize_i32, visit_i32, i32);
-    impl_for_deserialize_primitive!(deserialize_i64, visit_i64, i64);
+        while len != 0 {
+            self.update_slice();
 
-    impl_for_deserialize_primitive!(deserialize_u16, visit_u16, u16);
-    impl_for_deserialize_primitive!(deserialize_u32, visit_u32, u32);
-    impl_for_deserialize_primitive!(deserialize_u64, visit_u64, u64);
+            if self.slice.is_empty() {
+                return Err(Error::Eof);
+            }
 
-    impl_for_deserialize_primitive!(deserialize_f32, visit_f32, f32);
-    impl_for_deserialize_primitive!(deserialize_f64, visit_f64, f64);
+            let chunk = &self.slice[..self.slice.len().min(len)];
+            self.advance(chunk.len());
+            len -= chunk.len();
 
-    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        match char::from_u32(self.next_u32()?) {
-            Some(ch) => visitor.visit_char(ch),
-            None => Err(Error::InvalidChar),
+            f(chunk)?;
         }
-    }
 
-    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        match self.parse_bytes()? {
-            Cow::Owned(owned_bytes) => visitor.visit_string(String::from_utf8(owned_bytes)?),
-            Cow::Borrowed(bytes) => visitor.visit_borrowed_str(str::from_utf8(bytes)?),
-        }
+        Ok(())
     }
 
-    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        self.deserialize_str(visitor)
+    /// Read a length-prefixed bytes field as [`Bytes`].
+    ///
+    /// If the deserializer is created by [`Deserializer::from_bytes_chunks`]
+    /// and the field lies within a single chunk, it is a slice of that
+    /// chunk sharing its buffer, like [`Bytes::slice`]. Otherwise, it is
+    /// copied.
+    #[cfg(feature = "bytes")]
+    pub fn read_shared_bytes(&mut self) -> Result<Bytes> {
+        let chunks = self.chunks;
+
+        self.counted(|de| match de.parse_bytes()? {
+            Cow::Borrowed(bytes) => {
+                let bytes_range = bytes.as_ptr_range();
+                let chunk = chunks.iter().find(|chunk| {
+                    let range = chunk.as_ptr_range();
+                    range.start <= bytes_range.start && bytes_range.end <= range.end
+                });
+
+                Ok(match chunk {
+                    Some(chunk) => chunk.slice_ref(bytes),
+                    None => Bytes::copy_from_slice(bytes),
+                })
+            }
+            Cow::Owned(bytes) => Ok(Bytes::from(bytes)),
+        })
     }
 
-    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        match self.parse_bytes()? {
-            Cow::Owned(owned_bytes) => visitor.visit_byte_buf(owned_bytes),
-            Cow::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
-        }
+    /// Read an IP address string, same as [`crate::net::ip_str`].
+    pub fn read_ip(&mut self) -> Result<IpAddr> {
+        crate::net::ip_str::deserialize(self)
     }
 
-    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        self.deserialize_bytes(visitor)
+    /// Read an IP address string followed by a `u32` port, same as
+    /// [`crate::net::socket_addr`].
+    pub fn read_socket_addr(&mut self) -> Result<SocketAddr> {
+        crate::net::socket_addr::deserialize(self)
     }
 
-    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        visitor.visit_unit()
+    /// Is there any remaining data.
+    pub fn has_remaining_data(&mut self) -> bool {
+        self.update_slice();
+        !self.slice.is_empty()
     }
 
-    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        self.deserialize_unit(visitor)
+    /// Read the field count trailer appended by
+    /// [`crate::Serializer::finish_header`] and check it against
+    /// [`Self::field_count`], failing with [`Error::FieldCountMismatch`] if
+    /// they differ.
+    ///
+    /// It must be called once all fields are deserialized. Since the
+    /// trailer follows the last field, a trailing `Option` is always
+    /// decoded as `Some` from it and cannot be used with the trailer.
+    pub fn check_field_count_trailer(&mut self) -> Result<()> {
+        let expected = self.next_u32()?;
+
+        if expected != self.field_count {
+            return Err(Error::FieldCountMismatch {
+                expected,
+                found: self.field_count,
+            });
+        }
+        Ok(())
     }
 
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        visitor.visit_newtype_struct(self)
+    /// Is all input consumed, e.g. to check whether an optional trailing
+    /// field is present in a hand-written `Deserialize` impl.
+    ///
+    /// The next slice is pulled from the iterator if the current one is
+    /// exhausted.
+    pub fn is_at_end(&mut self) -> bool {
+        !self.has_remaining_data()
     }
+}
 
-    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        visitor.visit_seq(Access {
-            deserializer: self,
-            len,
-        })
-    }
+impl<'de, It> Input<'de> for Deserializer<'de, It>
+where
+    It: iter::FusedIterator + Iterator<Item = &'de [u8]>,
+{
+    fn next_byte(&mut self) -> Result<u8> {
+        self.update_slice();
 
-    fn deserialize_tuple_struct<V>(
-        self,
-        _name: &'static str,
-        len: usize,
-        visitor: V,
-    ) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        self.deserialize_tuple(len, visitor)
-    }
+        let byte = self.slice.first().copied().ok_or(Error::Eof)?;
+        self.advance(1);
 
-    fn deserialize_struct<V>(
-        self,
-        _name: &'static str,
-        fields: &'static [&'static str],
-        visitor: V,
-    ) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        self.deserialize_tuple(fields.len(), visitor)
+        Ok(byte)
     }
 
-    fn deserialize_enum<V>(
-        self,
-        _name: &'static str,
-        _variants: &'static [&'static str],
-        visitor: V,
-    ) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        impl<'a, 'de, It> serde::de::EnumAccess<'de> for &'a mut Deserializer<'de, It>
-        where
-            It: iter::FusedIterator + Iterator<Item = &'de [u8]>,
-        {
-            type Error = Error;
-            type Variant = Self;
+    fn fill_buffer(&mut self, mut buffer: &mut [u8]) -> Result<()> {
+        loop {
+            if buffer.is_empty() {
+                break Ok(());
+            }
 
-            fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
-            where
-                V: de::DeserializeSeed<'de>,
-            {
-                let idx: u32 = self.next_u32()?;
-                let val: Result<_> = seed.deserialize(idx.into_deserializer());
-                Ok((val?, self))
+            self.update_slice();
+
+            if self.slice.is_empty() {
+                break Err(Error::Eof);
             }
-        }
 
-        visitor.visit_enum(self)
-    }
+            let n = self.slice.len().min(buffer.len());
+
+            buffer[..n].copy_from_slice(&self.slice[..n]);
 
-    #[cfg(feature = "is_human_readable")]
-    /// Always return `false`
-    fn is_human_readable(&self) -> bool {
-        false
+            self.advance(n);
+            buffer = &mut buffer[n..];
+        }
     }
 
-    /// Unsupported
-    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        let len = self.next_u32()? as usize;
-        visitor.visit_seq(Access {
-            deserializer: self,
-            len,
-        })
+    /// The bytes are borrowed whenever they lie within the current slice,
+    /// which is always the case for contiguous input.
+    fn next_bytes(&mut self, size: usize) -> Result<Cow<'de, [u8]>> {
+        self.update_slice();
+
+        if self.slice.len() >= size {
+            let slice = &self.slice[..size];
+            self.advance(size);
+
+            Ok(Cow::Borrowed(slice))
+        } else if self.iter.size_hint().1 == Some(0) {
+            // No more input, e.g. in `from_bytes`, so fail without
+            // allocating a buffer for a bogus length.
+            Err(Error::Eof)
+        } else {
+            let mut bytes = vec![0_u8; size];
+            self.fill_buffer(&mut bytes)?;
+            Ok(Cow::Owned(bytes))
+        }
     }
 
-    /// Unsupported
-    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        Err(Error::Unsupported(&"deserialize_any"))
+    fn has_remaining_input(&mut self) -> Result<bool> {
+        Ok(self.has_remaining_data())
     }
 
-    /// Unsupported
-    fn deserialize_option<V>(self, _visitor: V) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        Err(Error::Unsupported(&"deserialize_option"))
+    /// The rest is borrowed if it is contiguous.
+    fn take_rest(&mut self) -> Result<Cow<'de, [u8]>> {
+        self.update_slice();
+        let first = mem::take(&mut self.slice);
+
+        let rest = match self.iter.find(|slice| !slice.is_empty()) {
+            None => Cow::Borrowed(first),
+            Some(second) => {
+                let mut bytes = [first, second].concat();
+                self.iter
+                    .by_ref()
+                    .for_each(|slice| bytes.extend_from_slice(slice));
+                Cow::Owned(bytes)
+            }
+        };
+        self.pos += rest.len();
+
+        Ok(rest)
+    }
+    fn max_len(&self) -> usize {
+        self.max_len
     }
 
-    /// Unsupported
-    fn deserialize_map<V>(self, _visitor: V) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        Err(Error::Unsupported(&"deserialize_map"))
+    fn endianness(&self) -> Endianness {
+        self.endianness
     }
 
-    /// Unsupported
-    fn deserialize_identifier<V>(self, _visitor: V) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        Err(Error::Unsupported(&"deserialize_identifier"))
+    fn begin_field(&mut self) {
+        if self.depth == 0 {
+            self.field_count += 1;
+        }
+        self.depth += 1;
     }
 
-    /// Unsupported
-    fn deserialize_ignored_any<V>(self, _visitor: V) -> Result<V::Value>
-    where
-        V: Visitor<'de>,
-    {
-        Err(Error::Unsupported(&"deserialize_ignored_any"))
+    fn end_field(&mut self) {
+        self.depth -= 1;
     }
 }
 
-impl<'a, 'de, It> VariantAccess<'de> for &'a mut Deserializer<'de, It>
-where
-    It: iter::FusedIterator + Iterator<Item = &'de [u8]>,
-{
-    type Error = Error;
-
-    fn unit_variant(self) -> Result<()> {
-        Ok(())
-    }
+macro_rules! impl_for_deserialize_primitive {
+    ( $name:ident, $visitor_fname:ident, $type:ty ) => {
+        fn $name<V>(self, visitor: V) -> Result<V::Value>
+        where
+            V: Visitor<'de>,
+        {
+            self.counted(|de| {
+                let bytes = de.next_bytes_const()?;
+                visitor.$visitor_fname(match Input::endianness(&*de) {
+                    Endianness::Big => <$type>::from_be_bytes(bytes),
+                    Endianness::Little => <$type>::from_le_bytes(bytes),
+                })
+            })
+        }
+    };
+}
+
+/// Implement the serde traits on `&mut $type`, which must implement
+/// [`Input`].
+macro_rules! impl_deserializer {
+    ( [ $( $generics:tt )* ] $type:ty where $( $bounds:tt )* ) => {
+        impl<$( $generics )*> de::Deserializer<'de> for &mut $type
+        where
+            $( $bounds )*
+        {
+            type Error = Error;
+
+            fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.counted(|de| match de.next_u32()? {
+                    1 => visitor.visit_bool(true),
+                    0 => visitor.visit_bool(false),
+                    _ if de.lenient_bool => visitor.visit_bool(true),
+                    _ => Err(Error::InvalidBoolEncoding),
+                })
+            }
+
+            fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.counted(|de| visitor.visit_u8(de.next_byte()?))
+            }
+
+            fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.counted(|de| visitor.visit_i8(de.next_byte()? as i8))
+            }
+
+            impl_for_deserialize_primitive!(deserialize_i16, visit_i16, i16);
+            impl_for_deserialize_primitive!(deserialize_i32, visit_i32, i32);
+            impl_for_deserialize_primitive!(deserialize_i64, visit_i64, i64);
+            impl_for_deserialize_primitive!(deserialize_i128, visit_i128, i128);
+
+            impl_for_deserialize_primitive!(deserialize_u16, visit_u16, u16);
+            impl_for_deserialize_primitive!(deserialize_u32, visit_u32, u32);
+            impl_for_deserialize_primitive!(deserialize_u64, visit_u64, u64);
+            impl_for_deserialize_primitive!(deserialize_u128, visit_u128, u128);
+
+            impl_for_deserialize_primitive!(deserialize_f32, visit_f32, f32);
+            impl_for_deserialize_primitive!(deserialize_f64, visit_f64, f64);
+
+            fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.counted(|de| {
+                    let v = de.next_u32()?;
+                    match char::from_u32(v) {
+                        Some(ch) => visitor.visit_char(ch),
+                        None => Err(Error::InvalidChar(v)),
+                    }
+                })
+            }
+
+            fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.counted(|de| match de.parse_bytes()? {
+                    Cow::Owned(owned_bytes) => match String::from_utf8(owned_bytes) {
+                        Ok(string) => visitor.visit_string(string),
+                        Err(err) if de.lossy_strings => {
+                            visitor.visit_string(String::from_utf8_lossy(err.as_bytes()).into_owned())
+                        }
+                        // Report the same `Utf8Error` as the borrowed path.
+                        Err(err) => Err(Error::InvalidStr(err.utf8_error())),
+                    },
+                    Cow::Borrowed(bytes) => match str::from_utf8(bytes) {
+                        Ok(s) => visitor.visit_borrowed_str(s),
+                        Err(_) if de.lossy_strings => {
+                            visitor.visit_string(String::from_utf8_lossy(bytes).into_owned())
+                        }
+                        Err(err) => Err(Error::InvalidStr(err)),
+                    },
+                })
+            }
+
+            fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.deserialize_str(visitor)
+            }
+
+            fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.counted(|de| match de.parse_bytes()? {
+                    Cow::Owned(owned_bytes) => visitor.visit_byte_buf(owned_bytes),
+                    Cow::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+                })
+            }
+
+            fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.deserialize_bytes(visitor)
+            }
+
+            fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                visitor.visit_unit()
+            }
+
+            fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.deserialize_unit(visitor)
+            }
+
+            fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                visitor.visit_newtype_struct(self)
+            }
+
+            /// Tuples and fixed-size arrays, e.g. `[u8; 4]`, are not prefixed
+            /// with their length since it is known from their type.
+            fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.counted(|de| {
+                    visitor.visit_seq(Access {
+                        deserializer: de,
+                        len,
+                    })
+                })
+            }
+
+            fn deserialize_tuple_struct<V>(
+                self,
+                name: &'static str,
+                len: usize,
+                visitor: V,
+            ) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.counted(|de| match name {
+                    crate::rest_vec::NAME => visitor.visit_seq(RestAccess { deserializer: de }),
+                    crate::rest_bytes::NAME => match de.take_rest()? {
+                        Cow::Owned(owned_bytes) => visitor.visit_byte_buf(owned_bytes),
+                        Cow::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+                    },
+                    _ => de.deserialize_tuple(len, visitor),
+                })
+            }
+
+            fn deserialize_struct<V>(
+                self,
+                _name: &'static str,
+                fields: &'static [&'static str],
+                visitor: V,
+            ) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.deserialize_tuple(fields.len(), visitor)
+            }
+
+            fn deserialize_enum<V>(
+                self,
+                _name: &'static str,
+                _variants: &'static [&'static str],
+                visitor: V,
+            ) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.counted(|de| visitor.visit_enum(de))
+            }
+
+            #[cfg(feature = "is_human_readable")]
+            /// Always return `false`
+            fn is_human_readable(&self) -> bool {
+                false
+            }
+
+            /// Sequences with variable length, e.g. `Vec<T>`, are prefixed with
+            /// their length as `u32`.
+            fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.counted(|de| {
+                    let len = de.next_u32()? as usize;
+                    visitor.visit_seq(Access {
+                        deserializer: de,
+                        len,
+                    })
+                })
+            }
+
+            /// Unsupported
+            fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                Err(Error::Unsupported(&"deserialize_any"))
+            }
+
+            /// `Option::None` is omitted on serialization, so the option is
+            /// `Some` if there is remaining data and `None` otherwise.
+            ///
+            /// Thus it only works for optional fields at the end of the message.
+            fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                if self.has_remaining_input()? {
+                    visitor.visit_some(self)
+                } else {
+                    visitor.visit_none()
+                }
+            }
+
+            /// Map is decoded as its length (`u32`) followed by key-value
+            /// pairs.
+            #[cfg(feature = "map")]
+            fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.counted(|de| {
+                    let len = de.next_u32()? as usize;
+                    visitor.visit_map(Access {
+                        deserializer: de,
+                        len,
+                    })
+                })
+            }
+
+            /// Unsupported
+            #[cfg(not(feature = "map"))]
+            fn deserialize_map<V>(self, _visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                Err(Error::Unsupported(&"deserialize_map"))
+            }
+
+            /// Unsupported
+            fn deserialize_identifier<V>(self, _visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                Err(Error::Unsupported(&"deserialize_identifier"))
+            }
+
+            /// Since the format is not self-describing, the value to ignore is
+            /// assumed to be a string or bytes, i.e. length(`u32`) + content,
+            /// which is skipped.
+            ///
+            /// Use [`crate::rest_bytes`] to skip all remaining data instead,
+            /// e.g. unknown trailing fields sent by a newer peer.
+            fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.counted(|de| {
+                    de.parse_bytes()?;
+                    visitor.visit_unit()
+                })
+            }
+        }
+
+        impl<$( $generics )*> EnumAccess<'de> for &mut $type
+        where
+            $( $bounds )*
+        {
+            type Error = Error;
+            type Variant = Self;
+
+            fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+            where
+                V: de::DeserializeSeed<'de>,
+            {
+                let idx: u32 = self.next_u32()?;
+                let val: Result<_> = seed.deserialize(idx.into_deserializer());
+                Ok((val?, self))
+            }
+        }
+
+        impl<$( $generics )*> VariantAccess<'de> for &mut $type
+        where
+            $( $bounds )*
+        {
+            type Error = Error;
+
+            fn unit_variant(self) -> Result<()> {
+                Ok(())
+            }
+
+            fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+            where
+                T: DeserializeSeed<'de>,
+            {
+                DeserializeSeed::deserialize(seed, self)
+            }
+
+            fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                de::Deserializer::deserialize_tuple(self, len, visitor)
+            }
+
+            fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+            }
+        }
+    };
+}
 
-    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+impl_deserializer!(
+    ['de, It] Deserializer<'de, It>
+    where
+        It: iter::FusedIterator + Iterator<Item = &'de [u8]>,
+);
+#[cfg(feature = "std")]
+impl_deserializer!(['de, R] crate::ReaderDeserializer<R> where R: io::Read,);
+
+struct Access<'a, D> {
+    deserializer: &'a mut D,
+    len: usize,
+}
+
+impl<'a, 'de, D> SeqAccess<'de> for Access<'a, D>
+where
+    for<'b> &'b mut D: de::Deserializer<'de, Error = Error>,
+{
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
     where
         T: DeserializeSeed<'de>,
     {
-        DeserializeSeed::deserialize(seed, self)
+        if self.len > 0 {
+            self.len -= 1;
+            let value = seed.deserialize(&mut *self.deserializer)?;
+            Ok(Some(value))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
     }
+}
 
-    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+/// `len` is the number of key-value pairs.
+#[cfg(feature = "map")]
+impl<'a, 'de, D> de::MapAccess<'de> for Access<'a, D>
+where
+    for<'b> &'b mut D: de::Deserializer<'de, Error = Error>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
     where
-        V: Visitor<'de>,
+        K: DeserializeSeed<'de>,
     {
-        de::Deserializer::deserialize_tuple(self, len, visitor)
+        if self.len > 0 {
+            self.len -= 1;
+            let key = seed.deserialize(&mut *self.deserializer)?;
+            Ok(Some(key))
+        } else {
+            Ok(None)
+        }
     }
 
-    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
     where
-        V: Visitor<'de>,
+        V: DeserializeSeed<'de>,
     {
-        de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+        seed.deserialize(&mut *self.deserializer)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
     }
 }
 
-struct Access<'a, 'de, It> {
-    deserializer: &'a mut Deserializer<'de, It>,
-    len: usize,
+/// Yield elements until the input is exhausted.
+struct RestAccess<'a, D> {
+    deserializer: &'a mut D,
 }
 
-impl<'a, 'de, It> SeqAccess<'de> for Access<'a, 'de, It>
+impl<'a, 'de, D> SeqAccess<'de> for RestAccess<'a, D>
 where
-    It: iter::FusedIterator + Iterator<Item = &'de [u8]>,
+    D: Input<'de>,
+    for<'b> &'b mut D: de::Deserializer<'de, Error = Error>,
 {
     type Error = Error;
 
@@ -442,18 +1031,12 @@ where
     where
         T: DeserializeSeed<'de>,
     {
-        if self.len > 0 {
-            self.len -= 1;
-            let value = seed.deserialize(&mut *self.deserializer)?;
-            Ok(Some(value))
+        if self.deserializer.has_remaining_input()? {
+            seed.deserialize(&mut *self.deserializer).map(Some)
         } else {
             Ok(None)
         }
     }
-
-    fn size_hint(&self) -> Option<usize> {
-        Some(self.len)
-    }
 }
 
 /// Test deserialization
@@ -522,6 +1105,16 @@ mod tests {
         test_roundtrip(&0x1234_u16);
         test_roundtrip(&0x12345678_u32);
         test_roundtrip(&0x1234567887654321_u64);
+        test_roundtrip(&0x0123456789abcdef0123456789abcdef_u128);
+        test_roundtrip(&0x8000000000000000000000000000000f_u128);
+        test_roundtrip(&u128::MAX);
+        test_roundtrip(&-0x0123456789abcdef0123456789abcdef_i128);
+        test_roundtrip(&i128::MIN);
+        test_roundtrip(&i128::MAX);
+
+        // 128-bit integers need all of their 16 bytes
+        assert_matches!(from_bytes::<u128>(&[0xff; 15]), Err(Error::Eof));
+        assert_matches!(from_bytes::<i128>(&[0xff; 15]), Err(Error::Eof));
     }
 
     #[test]
@@ -530,6 +1123,30 @@ mod tests {
         test_roundtrip(&false);
     }
 
+    #[test]
+    fn test_char() {
+        for (ch, encoded) in [
+            ('a', [0, 0, 0, 0x61]),
+            ('\u{FFFF}', [0, 0, 0xff, 0xff]),
+            ('😀', [0, 0x01, 0xf6, 0x00]),
+            (char::MAX, [0, 0x10, 0xff, 0xff]),
+        ] {
+            let serialized = to_bytes(&ch).unwrap();
+            assert_eq!(serialized[4..], encoded);
+
+            test_roundtrip(&ch);
+        }
+
+        // Surrogates and values above `char::MAX` are not valid `char`.
+        for invalid in [0xD800_u32, 0xDFFF, 0x110000, u32::MAX] {
+            let serialized = to_bytes(&invalid).unwrap();
+            assert_matches!(
+                from_bytes::<char>(&serialized[4..]),
+                Err(Error::InvalidChar(v)) if v == invalid
+            );
+        }
+    }
+
     #[test]
     fn test_str() {
         let s = "Hello, world!";
@@ -545,11 +1162,151 @@ mod tests {
         test_roundtrip(&vec![0x0010_u16, 0x0100_u16, 0x1034_u16, 0x7812_u16]);
     }
 
+    #[test]
+    fn test_empty_seq() {
+        #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+        struct S {
+            v: Vec<u32>,
+            n: u32,
+        }
+
+        let value = S {
+            v: Vec::new(),
+            n: 0x12345678,
+        };
+
+        let serialized = to_bytes(&value).unwrap();
+        assert_eq!(serialized[4..], [0, 0, 0, 0, 0x12, 0x34, 0x56, 0x78]);
+
+        test_roundtrip(&value);
+
+        // The zero count does not consume any following bytes.
+        let (deserialized, trailing) = from_bytes::<Vec<u32>>(&serialized[4..]).unwrap();
+        assert!(deserialized.is_empty());
+        assert_eq!(trailing, [0x12, 0x34, 0x56, 0x78]);
+    }
+
+    #[test]
+    fn test_nested_seq() {
+        let value = vec![
+            b"abc".to_vec(),
+            Vec::new(),
+            vec![0xff],
+            Vec::new(),
+            (0..=255).collect(),
+        ];
+
+        let serialized = to_bytes(&value).unwrap();
+        let body = &serialized[4..];
+        // The outer count is the number of elements, not bytes.
+        assert_eq!(body[..4], [0, 0, 0, 5]);
+        assert_eq!(body[4..11], [0, 0, 0, 3, b'a', b'b', b'c']);
+        assert_eq!(body[11..20], [0, 0, 0, 0, 0, 0, 0, 1, 0xff]);
+        assert_eq!(body[20..24], [0, 0, 0, 0]);
+        assert_eq!(body[24..28], [0, 0, 1, 0]);
+        assert_eq!(body.len(), 28 + 256);
+
+        test_roundtrip(&value);
+        test_roundtrip(&Vec::<Vec<u8>>::new());
+        test_roundtrip(&vec![Vec::<u8>::new(); 3]);
+
+        // Trailing data after the outer seq is left untouched.
+        let serialized = to_bytes(&(vec![vec![1_u8], Vec::new()], 0x12345678_u32)).unwrap();
+        let (deserialized, trailing) = from_bytes::<Vec<Vec<u8>>>(&serialized[4..]).unwrap();
+        assert_eq!(deserialized, [vec![1], Vec::new()]);
+        assert_eq!(trailing, [0x12, 0x34, 0x56, 0x78]);
+    }
+
+    #[test]
+    fn test_fixed_array() {
+        test_roundtrip(&[0x12345678_u32, 0, 1, 2, 3, 4, 5, u32::MAX]);
+        test_roundtrip(&core::array::from_fn::<u8, 32, _>(|i| i as u8));
+    }
+
+    #[test]
+    fn test_array_vs_vec() {
+        let array = [0x0102_u16, 0x0304, 0x0506, 0x0708];
+
+        // Arrays are written without length prefix
+        let serialized = to_bytes(&array).unwrap();
+        assert_eq!(serialized[4..], [1, 2, 3, 4, 5, 6, 7, 8]);
+        test_roundtrip(&array);
+
+        // While `Vec` and slices are prefixed with their length
+        let serialized = to_bytes(&array.to_vec()).unwrap();
+        assert_eq!(serialized[4..], [0, 0, 0, 4, 1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(serialized, to_bytes(&&array[..]).unwrap());
+        test_roundtrip(&array.to_vec());
+    }
+
     #[test]
     fn test_tuple() {
         test_roundtrip(&(0x00_u8, 0x0100_u16, 0x1034_u16, 0x7812_u16));
     }
 
+    #[test]
+    fn test_mixed_tuple() {
+        type Mixed<'a> = (u32, &'a str, Vec<u8>, &'a [u8], String);
+
+        let value: Mixed = (
+            0x12345678,
+            "borrowed",
+            vec![1, 2, 3],
+            b"bytes",
+            "owned".to_owned(),
+        );
+        let serialized = to_bytes(&value).unwrap();
+        let body = &serialized[4..];
+
+        let ((n, s, vec, bytes, string), trailing) = from_bytes::<Mixed>(body).unwrap();
+        assert_eq!(
+            (n, s, &vec, bytes, &string),
+            (value.0, value.1, &value.2, value.3, &value.4)
+        );
+        assert!(trailing.is_empty());
+
+        // Borrowable elements point into the input.
+        let input = body.as_ptr_range();
+        assert!(input.contains(&s.as_ptr()));
+        assert!(input.contains(&bytes.as_ptr()));
+        assert_eq!(&body[8..16], s.as_bytes());
+
+        // Owned elements do not.
+        assert!(!input.contains(&vec.as_ptr()));
+        assert!(!input.contains(&string.as_ptr()));
+    }
+
+    #[test]
+    fn test_cow_bytes_borrowed() {
+        #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+        struct S<'a> {
+            id: u32,
+            #[serde(borrow)]
+            data: Cow<'a, [u8]>,
+            #[serde(borrow)]
+            empty: Cow<'a, [u8]>,
+        }
+
+        let value = S {
+            id: 1,
+            data: Cow::Borrowed(b"\x00zero-copy\xff"),
+            empty: Cow::Borrowed(b""),
+        };
+        let serialized = to_bytes(&value).unwrap();
+        let body = &serialized[4..];
+
+        let (deserialized, trailing) = from_bytes::<S>(body).unwrap();
+        assert_eq!(deserialized, value);
+        assert!(trailing.is_empty());
+
+        match (&deserialized.data, &deserialized.empty) {
+            (Cow::Borrowed(data), Cow::Borrowed(_)) => {
+                assert_eq!(data.as_ptr(), body[8..].as_ptr());
+            }
+            _ => panic!("expected borrowed bytes, got {:?}", deserialized),
+        }
+    }
+
     #[test]
     fn test_struct() {
         #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
@@ -587,6 +1344,750 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_from_slices() {
+        #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+        struct S {
+            v1: u32,
+            v2: String,
+            v3: u16,
+        }
+        let value = S {
+            v1: 0x12345678,
+            v2: "Hello, world!".to_owned(),
+            v3: 0x7812,
+        };
+
+        let serialized = to_bytes(&value).unwrap();
+        let body = &serialized[4..];
+
+        let slices = [
+            &body[..3],
+            &[][..],
+            &body[3..10],
+            &body[10..11],
+            &body[11..],
+        ];
+        let mut deserializer = Deserializer::from_slices(&slices);
+        assert_eq!(S::deserialize(&mut deserializer).unwrap(), value);
+        assert!(!deserializer.has_remaining_data());
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_from_bytes_chunks() {
+        use bytes::Bytes;
+
+        let serialized = to_bytes(&(0x12_u8, "Hello", &b"world"[..], &b"!"[..])).unwrap();
+        let body = Bytes::from(serialized).slice(4..);
+
+        let chunks = [body.slice(..3), body.slice(3..10), body.slice(10..)];
+        let mut deserializer = Deserializer::from_bytes_chunks(&chunks);
+
+        // Both lie within a single chunk, so they are borrowed from it
+        let (_, s) = <(u8, &str)>::deserialize(&mut deserializer).unwrap();
+        assert_eq!(s, "Hello");
+        assert_eq!(s.as_ptr(), chunks[1][2..].as_ptr());
+
+        let shared = deserializer.read_shared_bytes().unwrap();
+        assert_eq!(shared, &b"world"[..]);
+        assert_eq!(shared.as_ptr(), chunks[2][4..].as_ptr());
+
+        // Spanning multiple chunks requires a copy
+        let chunks = [body.slice(..7), body.slice(7..16), body.slice(16..)];
+        let mut deserializer = Deserializer::from_bytes_chunks(&chunks);
+        assert_eq!(
+            <(u8, String)>::deserialize(&mut deserializer).unwrap(),
+            (0x12, "Hello".to_owned())
+        );
+
+        let copied = deserializer.read_shared_bytes().unwrap();
+        assert_eq!(copied, &b"world"[..]);
+        assert!(!chunks
+            .iter()
+            .any(|chunk| chunk.as_ptr_range().contains(&copied.as_ptr())));
+
+        let shared = deserializer.read_shared_bytes().unwrap();
+        assert_eq!(shared, &b"!"[..]);
+        assert_eq!(shared.as_ptr(), chunks[2][7..].as_ptr());
+        assert!(deserializer.is_at_end());
+    }
+
+    #[test]
+    fn test_reset() {
+        let first = to_bytes(&(1_u8, "Hello", 2_u32)).unwrap();
+        let second = to_bytes(&"world").unwrap();
+
+        let mut deserializer = Deserializer::from_bytes(&first[4..]).max_len(5);
+        assert_eq!(
+            <(u8, &str)>::deserialize(&mut deserializer).unwrap(),
+            (1, "Hello")
+        );
+        assert!(deserializer.has_remaining_data());
+
+        deserializer.reset(&second[4..]);
+        assert_eq!(deserializer.position(), 0);
+        assert_eq!(<&str>::deserialize(&mut deserializer).unwrap(), "world");
+        assert!(!deserializer.has_remaining_data());
+
+        // Settings are kept
+        let third = to_bytes(&"Hello, world").unwrap();
+        deserializer.reset(&third[4..]);
+        assert_matches!(
+            <&str>::deserialize(&mut deserializer),
+            Err(Error::LengthLimitExceeded {
+                len: 12,
+                max_len: 5
+            })
+        );
+    }
+
+    #[test]
+    fn test_read_socket_addr() {
+        let serialized = to_bytes(&("::1", "127.0.0.1", 22_u32)).unwrap();
+        let body = &serialized[4..];
+
+        for chunk_size in 1..=body.len() {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+            assert_eq!(
+                deserializer.read_ip().unwrap(),
+                IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1])
+            );
+            assert_eq!(
+                deserializer.read_socket_addr().unwrap(),
+                SocketAddr::from(([127, 0, 0, 1], 22))
+            );
+            assert!(!deserializer.has_remaining_data());
+        }
+    }
+
+    #[test]
+    fn test_result() {
+        type Response = std::result::Result<u32, String>;
+
+        let ok: Response = Ok(0x12345678);
+        let serialized = to_bytes(&ok).unwrap();
+        assert_eq!(serialized[4..], [0, 0, 0, 0, 0x12, 0x34, 0x56, 0x78]);
+        test_roundtrip(&ok);
+
+        let err: Response = Err("denied".to_owned());
+        let serialized = to_bytes(&err).unwrap();
+        assert_eq!(serialized[4..8], [0, 0, 0, 1]);
+        assert_eq!(serialized[8..], *b"\0\0\0\x06denied");
+        test_roundtrip(&err);
+
+        let serialized = to_bytes(&2_u32).unwrap();
+        assert_matches!(
+            from_bytes::<Response>(&serialized[4..]),
+            Err(Error::Message(_))
+        );
+    }
+
+    #[test]
+    fn test_arc() {
+        use std::sync::Arc;
+
+        #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+        struct Shared {
+            name: Arc<str>,
+            data: Arc<[u8]>,
+        }
+
+        let value = Shared {
+            name: Arc::from("Hello, world!"),
+            data: Arc::from(&b"\x00\x01\xff"[..]),
+        };
+
+        // Encoded the same as `String` and `Vec<u8>`.
+        let serialized = to_bytes(&value).unwrap();
+        assert_eq!(
+            serialized,
+            to_bytes(&("Hello, world!", vec![0_u8, 1, 0xff])).unwrap()
+        );
+
+        test_roundtrip(&value);
+        test_roundtrip(&Shared {
+            name: Arc::from(""),
+            data: Arc::from(Vec::new()),
+        });
+    }
+
+    #[test]
+    fn test_newtype_struct() {
+        #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+        struct Id(u32);
+
+        #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+        struct Name(String);
+
+        #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+        struct Pair((u8, u16));
+
+        #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+        struct A(B);
+
+        #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+        struct B(u32);
+
+        test_roundtrip(&Id(0x1234));
+        test_roundtrip(&Name("Hello, world!".to_owned()));
+        test_roundtrip(&Pair((1, 0x0203)));
+        test_roundtrip(&A(B(0x12345678)));
+
+        // Newtypes can be deserialized from their inner type and vice versa
+        let serialized = to_bytes(&0x12345678_u32).unwrap();
+        assert_eq!(
+            from_bytes::<A>(&serialized[4..]).unwrap().0,
+            A(B(0x12345678))
+        );
+    }
+
+    #[test]
+    fn test_serde_bytes() {
+        use std::borrow::Cow;
+
+        use serde_bytes::ByteBuf;
+
+        #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+        struct Packet<'a> {
+            #[serde(with = "serde_bytes")]
+            borrowed: &'a [u8],
+            owned: ByteBuf,
+            #[serde(borrow, with = "serde_bytes")]
+            cow: Cow<'a, [u8]>,
+        }
+
+        let value = Packet {
+            borrowed: b"borrowed",
+            owned: ByteBuf::from(b"owned".to_vec()),
+            cow: Cow::Borrowed(b"cow"),
+        };
+        let serialized = to_bytes(&value).unwrap();
+        let body = &serialized[4..];
+
+        // Contiguous input is borrowed without copying
+        let (packet, _) = from_bytes::<Packet>(body).unwrap();
+        assert_eq!(packet, value);
+        assert!(body.as_ptr_range().contains(&packet.borrowed.as_ptr()));
+        assert_matches!(packet.cow, Cow::Borrowed(cow) if body.as_ptr_range().contains(&cow.as_ptr()));
+
+        // `ByteBuf` and `Cow` also support bytes split across chunks
+        #[derive(Debug, Eq, PartialEq, Deserialize)]
+        struct OwnedPacket<'a> {
+            owned: ByteBuf,
+            #[serde(borrow, with = "serde_bytes")]
+            cow: Cow<'a, [u8]>,
+        }
+
+        let body = &body[12..];
+        for chunk_size in 1..=body.len() {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+            let packet = OwnedPacket::deserialize(&mut deserializer).unwrap();
+            assert_eq!(packet.owned, value.owned);
+            assert_eq!(packet.cow, value.cow);
+        }
+    }
+
+    #[test]
+    fn test_overlong_len() {
+        let body = [0xff, 0xff, 0xff, 0xff, b'a'];
+
+        assert_matches!(from_bytes::<&[u8]>(&body), Err(Error::Eof));
+        assert_matches!(from_bytes::<String>(&body), Err(Error::Eof));
+    }
+
+    #[test]
+    fn test_from_bytes_consumed() {
+        let serialized = to_bytes(&(1_u32, "Hello", 2_u8)).unwrap();
+        let body = &serialized[4..];
+
+        let ((n, s), consumed) = from_bytes_consumed::<(u32, &str)>(body).unwrap();
+        assert_eq!((n, s), (1, "Hello"));
+        assert_eq!(consumed, 13);
+        assert_eq!(body[consumed..], [2]);
+
+        let (_, consumed) = from_bytes_consumed::<(u32, &str, u8)>(body).unwrap();
+        assert_eq!(consumed, body.len());
+    }
+
+    #[test]
+    fn test_from_bytes_framed() {
+        let mut buffer = to_bytes(&(0x1234_u16, "Hello")).unwrap();
+        buffer.extend(to_bytes(&0x12345678_u32).unwrap());
+
+        let (first, rest) = from_bytes_framed::<(u16, &str)>(&buffer).unwrap();
+        assert_eq!(first, (0x1234, "Hello"));
+
+        let (second, rest) = from_bytes_framed::<u32>(rest).unwrap();
+        assert_eq!(second, 0x12345678);
+        assert!(rest.is_empty());
+
+        // The frame limits how far `T` can be deserialized
+        let buffer = to_bytes(&0x1234_u16).unwrap();
+        assert_matches!(from_bytes_framed::<u32>(&buffer), Err(Error::Eof));
+
+        // Truncated frame
+        assert_matches!(
+            from_bytes_framed::<u16>(&buffer[..buffer.len() - 1]),
+            Err(Error::Eof)
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_self_len() {
+        let mut buffer = to_bytes(&(0x1234_u16, "Hello")).unwrap();
+        assert_eq!(
+            from_bytes_self_len::<(u16, &str)>(&buffer).unwrap(),
+            (0x1234, "Hello")
+        );
+
+        buffer.push(0);
+        assert_matches!(
+            from_bytes_self_len::<(u16, &str)>(&buffer),
+            Err(Error::InvalidLength {
+                expected: 11,
+                found: 12
+            })
+        );
+
+        buffer.truncate(buffer.len() - 2);
+        assert_matches!(
+            from_bytes_self_len::<(u16, &str)>(&buffer),
+            Err(Error::InvalidLength {
+                expected: 11,
+                found: 10
+            })
+        );
+
+        assert_matches!(from_bytes_self_len::<u8>(&[0, 0]), Err(Error::Eof));
+    }
+
+    #[test]
+    fn test_decode_all() {
+        assert_eq!(decode_all::<u32>(&[]).count(), 0);
+
+        let mut buffer = to_bytes(&(1_u32, "a")).unwrap();
+        let decoded: Vec<(u32, String)> = decode_all(&buffer).collect::<Result<_>>().unwrap();
+        assert_eq!(decoded, [(1, "a".to_owned())]);
+
+        for i in 2..5 {
+            buffer.extend(to_bytes(&(i, "b".repeat(i as usize))).unwrap());
+        }
+        let decoded: Vec<(u32, &str)> = decode_all(&buffer).collect::<Result<_>>().unwrap();
+        assert_eq!(decoded, [(1, "a"), (2, "bb"), (3, "bbb"), (4, "bbbb")]);
+
+        // Stops after the truncated frame
+        let mut iter = decode_all::<(u32, &str)>(&buffer[..buffer.len() - 1]);
+        assert_eq!(iter.by_ref().take(3).count(), 3);
+        assert_matches!(iter.next(), Some(Err(Error::Eof)));
+        assert_matches!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_lossy_strings() {
+        let serialized = to_bytes(&(0x12_u8, &b"Hello\xffworld"[..])).unwrap();
+        let body = &serialized[4..];
+
+        // Test both the borrowed and owned path
+        for chunk_size in [body.len(), 3] {
+            let deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+
+            assert_matches!(
+                <(u8, String)>::deserialize(&mut deserializer.clone()),
+                Err(Error::InvalidStr(_))
+            );
+
+            let mut deserializer = deserializer.lossy_strings(true);
+            assert_eq!(
+                <(u8, String)>::deserialize(&mut deserializer).unwrap(),
+                (0x12, "Hello\u{FFFD}world".to_owned())
+            );
+        }
+    }
+
+    #[test]
+    fn test_lenient_bool() {
+        let serialized = to_bytes(&(0_u32, 1_u32, 2_u32, u32::MAX)).unwrap();
+        let body = &serialized[4..];
+
+        assert_matches!(
+            from_bytes::<(bool, bool, bool, bool)>(body),
+            Err(Error::InvalidBoolEncoding)
+        );
+
+        let mut deserializer = Deserializer::from_bytes(body).lenient_bool(true);
+        assert_eq!(
+            <(bool, bool, bool, bool)>::deserialize(&mut deserializer).unwrap(),
+            (false, true, true, true)
+        );
+
+        #[cfg(feature = "std")]
+        {
+            let mut deserializer = crate::ReaderDeserializer::new(body).lenient_bool(true);
+            assert_eq!(
+                <(bool, bool, bool, bool)>::deserialize(&mut deserializer).unwrap(),
+                (false, true, true, true)
+            );
+        }
+    }
+
+    #[test]
+    fn test_max_len() {
+        let serialized = to_bytes(&(0x12_u8, "Hello", &b"world!"[..])).unwrap();
+        let body = &serialized[4..];
+
+        let mut deserializer = Deserializer::from_bytes(body).max_len(6);
+        assert_eq!(
+            <(u8, &str, &[u8])>::deserialize(&mut deserializer).unwrap(),
+            (0x12, "Hello", &b"world!"[..])
+        );
+
+        let mut deserializer = Deserializer::from_bytes(body).max_len(5);
+        assert_matches!(
+            <(u8, &str, &[u8])>::deserialize(&mut deserializer),
+            Err(Error::LengthLimitExceeded { len: 6, max_len: 5 })
+        );
+
+        // Rejected before reaching the end of the input
+        let body = [0xff, 0xff, 0xff, 0xff, b'a'];
+        let mut deserializer = Deserializer::from_bytes(&body).max_len(1024);
+        assert_matches!(
+            String::deserialize(&mut deserializer),
+            Err(Error::LengthLimitExceeded { max_len: 1024, .. })
+        );
+    }
+
+    #[test]
+    fn test_endianness() {
+        #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+        enum Request {
+            Ping,
+            Open { id: u64, path: String, flag: bool },
+        }
+
+        let body = [
+            1, 0, 0, 0, // variant index
+            0x78, 0x56, 0x34, 0x12, 0, 0, 0, 0, // id
+            2, 0, 0, 0, b'/', b'a', // path
+            1, 0, 0, 0, // flag
+        ];
+        let expected = Request::Open {
+            id: 0x1234_5678,
+            path: "/a".to_owned(),
+            flag: true,
+        };
+
+        for chunk_size in 1..=body.len() {
+            let mut deserializer =
+                Deserializer::new(body.chunks(chunk_size).fuse()).endianness(Endianness::Little);
+            assert_eq!(Request::deserialize(&mut deserializer).unwrap(), expected);
+        }
+
+        let deserializer = Deserializer::from_bytes(&body).endianness(Endianness::Little);
+        assert_eq!(deserializer.peek_variant_index(), Some(1));
+
+        #[cfg(feature = "std")]
+        {
+            let mut deserializer =
+                crate::ReaderDeserializer::new(&body[..]).endianness(Endianness::Little);
+            assert_eq!(Request::deserialize(&mut deserializer).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_ignored_any() {
+        use serde::de::IgnoredAny;
+
+        #[derive(Debug, Deserialize)]
+        struct Known {
+            id: u32,
+            _unknown: IgnoredAny,
+            name: String,
+        }
+
+        // The ignored value is followed by another field
+        let serialized = to_bytes(&(1_u32, "unknown", "Hello")).unwrap();
+        let body = &serialized[4..];
+
+        for chunk_size in 1..=body.len() {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+            let known = Known::deserialize(&mut deserializer).unwrap();
+            assert_eq!((known.id, known.name.as_str()), (1, "Hello"));
+            assert_eq!(deserializer.position(), body.len());
+            assert!(deserializer.is_at_end());
+        }
+
+        let serialized = to_bytes(&(1_u32, b"\x00\xff".as_ref(), "Hello", 2_u8)).unwrap();
+        let (known, trailing) = from_bytes::<Known>(&serialized[4..]).unwrap();
+        assert_eq!((known.id, known.name.as_str()), (1, "Hello"));
+        assert_eq!(trailing, [2]);
+
+        // Truncated ignored value
+        let serialized = to_bytes(&(1_u32, "unknown")).unwrap();
+        assert_matches!(
+            from_bytes::<Known>(&serialized[4..serialized.len() - 1]),
+            Err(Error::Eof)
+        );
+    }
+
+    #[test]
+    fn test_check_field_count_trailer() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum E {
+            A(u8, u8),
+            B { x: u8 },
+        }
+
+        let mut serializer: crate::Serializer =
+            crate::Serializer::default().enable_field_count_trailer(true);
+        (1_u8, vec![2_u16, 3]).serialize(&mut serializer).unwrap();
+        "s".serialize(&mut serializer).unwrap();
+        E::A(4, 5).serialize(&mut serializer).unwrap();
+        E::B { x: 6 }.serialize(&mut serializer).unwrap();
+        serializer.finish_header(0).unwrap();
+        let body = serializer.output;
+
+        for chunk_size in 1..=body.len() {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+            assert_eq!(
+                <(u8, Vec<u16>)>::deserialize(&mut deserializer).unwrap(),
+                (1, vec![2, 3])
+            );
+            assert_eq!(<&str>::deserialize(&mut deserializer).unwrap(), "s");
+            assert_eq!(E::deserialize(&mut deserializer).unwrap(), E::A(4, 5));
+            assert_eq!(E::deserialize(&mut deserializer).unwrap(), E::B { x: 6 });
+            assert_eq!(deserializer.field_count(), 4);
+
+            deserializer.check_field_count_trailer().unwrap();
+            assert!(deserializer.is_at_end());
+        }
+
+        // A field is missing
+        let mut deserializer = Deserializer::from_bytes(&body);
+        <(u8, Vec<u16>, &str)>::deserialize(&mut deserializer).unwrap();
+        <(E, E)>::deserialize(&mut deserializer).unwrap();
+        assert_matches!(
+            deserializer.check_field_count_trailer(),
+            Err(Error::FieldCountMismatch {
+                expected: 4,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_remaining_slice() {
+        let serialized = to_bytes(&(1_u32, 2_u8)).unwrap();
+        let body = &serialized[4..];
+
+        let mut deserializer = Deserializer::from_bytes(body);
+        assert_eq!(deserializer.remaining_slice(), body);
+
+        u32::deserialize(&mut deserializer).unwrap();
+        assert_eq!(deserializer.remaining_slice(), [2]);
+        assert!(!deserializer.is_at_end());
+
+        u8::deserialize(&mut deserializer).unwrap();
+        assert!(deserializer.remaining_slice().is_empty());
+        assert!(deserializer.is_at_end());
+
+        // The current slice is exhausted but the iterator is not
+        let mut deserializer = Deserializer::new(body.chunks(4).fuse());
+        u32::deserialize(&mut deserializer).unwrap();
+        assert!(deserializer.remaining_slice().is_empty());
+        assert!(!deserializer.is_at_end());
+        assert_eq!(deserializer.remaining_slice(), [2]);
+    }
+
+    #[test]
+    fn test_position() {
+        let serialized = to_bytes(&(0x12_u8, "Hello", 2_u32, true)).unwrap();
+        let mut body = serialized[4..].to_vec();
+        // Corrupt the bool
+        *body.last_mut().unwrap() = 2;
+
+        for chunk_size in 1..=body.len() {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+            assert_eq!(deserializer.position(), 0);
+
+            <(u8, String)>::deserialize(&mut deserializer).unwrap();
+            assert_eq!(deserializer.position(), 10);
+
+            u32::deserialize(&mut deserializer).unwrap();
+            assert_eq!(deserializer.position(), 14);
+
+            assert_matches!(
+                bool::deserialize(&mut deserializer),
+                Err(Error::InvalidBoolEncoding)
+            );
+            assert_eq!(deserializer.position(), body.len());
+        }
+
+        // Truncated input
+        let mut deserializer = Deserializer::from_bytes(&body[..12]);
+        assert_matches!(
+            <(u8, String, u32)>::deserialize(&mut deserializer),
+            Err(Error::Eof)
+        );
+        assert_eq!(deserializer.position(), 12);
+    }
+
+    #[cfg(feature = "map")]
+    #[test]
+    fn test_map() {
+        use std::collections::BTreeMap;
+
+        test_roundtrip(&BTreeMap::<String, u64>::new());
+        test_roundtrip(&BTreeMap::from([
+            ("a".to_owned(), 1_u64),
+            ("bc".to_owned(), u64::MAX),
+        ]));
+        test_roundtrip(&(
+            1_u8,
+            BTreeMap::from([(2_u32, vec![3_u16]), (4, Vec::new())]),
+            5_u8,
+        ));
+
+        let serialized = to_bytes(&BTreeMap::from([(1_u32, 2_u32)])).unwrap();
+        assert_matches!(
+            from_bytes::<BTreeMap<u32, u32>>(&serialized[4..serialized.len() - 1]),
+            Err(Error::Eof)
+        );
+    }
+
+    #[cfg(not(feature = "map"))]
+    #[test]
+    fn test_map_unsupported() {
+        use std::collections::BTreeMap;
+
+        assert_matches!(
+            from_bytes::<BTreeMap<u32, u32>>(&[0, 0, 0, 0]),
+            Err(Error::Unsupported(_))
+        );
+    }
+
+    #[test]
+    fn test_trailing_option() {
+        #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+        struct S {
+            id: u32,
+            name: Option<String>,
+        }
+
+        test_roundtrip(&S {
+            id: 1,
+            name: Some("Hello".to_owned()),
+        });
+        test_roundtrip(&S { id: 1, name: None });
+
+        #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+        struct Nested {
+            id: u32,
+            inner: Option<(u16, Option<u8>)>,
+        }
+
+        test_roundtrip(&Nested {
+            id: 1,
+            inner: Some((2, Some(3))),
+        });
+        test_roundtrip(&Nested {
+            id: 1,
+            inner: Some((2, None)),
+        });
+        test_roundtrip(&Nested { id: 1, inner: None });
+
+        assert_eq!(
+            from_bytes::<(u8, Option<u32>)>(&[1]).unwrap(),
+            ((1, None), &[][..])
+        );
+    }
+
+    #[test]
+    fn test_peek_variant_index() {
+        #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+        enum E {
+            A(u8),
+            B(u32),
+        }
+
+        let serialized = to_bytes(&(E::B(7), E::A(1))).unwrap();
+        let mut deserializer = Deserializer::from_bytes(&serialized[4..]);
+
+        assert_eq!(deserializer.peek_variant_index(), Some(1));
+        assert_eq!(deserializer.peek_variant_index(), Some(1));
+        assert_eq!(E::deserialize(&mut deserializer).unwrap(), E::B(7));
+
+        assert_eq!(deserializer.peek_variant_index(), Some(0));
+        assert_eq!(E::deserialize(&mut deserializer).unwrap(), E::A(1));
+
+        assert_eq!(deserializer.peek_variant_index(), None);
+        assert!(!deserializer.has_remaining_data());
+    }
+
+    #[test]
+    fn test_read_bytes_streaming() {
+        let payload: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let serialized = to_bytes(&(&payload, 0x12_u8)).unwrap();
+        let body = &serialized[4..];
+
+        for chunk_size in [1, 3, 64, body.len()] {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+
+            let mut collected = Vec::new();
+            let mut max_chunk_len = 0;
+            deserializer
+                .read_bytes_streaming(|chunk| {
+                    max_chunk_len = max_chunk_len.max(chunk.len());
+                    collected.extend_from_slice(chunk);
+                    Ok(())
+                })
+                .unwrap();
+
+            assert_eq!(collected, payload);
+            // Chunks are borrowed from the input instead of being collected.
+            assert!(max_chunk_len <= chunk_size);
+
+            assert_eq!(u8::deserialize(&mut deserializer).unwrap(), 0x12);
+        }
+
+        // Truncated
+        let mut deserializer = Deserializer::from_bytes(&body[..100]);
+        assert_matches!(
+            deserializer.read_bytes_streaming(|_| Ok(())),
+            Err(Error::Eof)
+        );
+
+        // Error from the callback
+        let mut deserializer = Deserializer::from_bytes(body);
+        assert_matches!(
+            deserializer.read_bytes_streaming(|_| Err(Error::TooLong)),
+            Err(Error::TooLong)
+        );
+    }
+
+    #[test]
+    fn test_invalid_str() {
+        for invalid in [&b"abc\xe2\x28\xa1def"[..], b"abc\xe2", b"\xff"] {
+            let expected = str::from_utf8(invalid).unwrap_err();
+
+            let serialized = to_bytes(&(0x12_u8, invalid)).unwrap();
+            let body = &serialized[4..];
+
+            for chunk_size in 1..=body.len() {
+                let deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+
+                assert_matches!(
+                    <(u8, &str)>::deserialize(&mut deserializer.clone()),
+                    Err(Error::InvalidStr(err)) if err == expected
+                );
+                assert_matches!(
+                    <(u8, String)>::deserialize(&mut deserializer.clone()),
+                    Err(Error::InvalidStr(err)) if err == expected
+                );
+            }
+        }
+    }
+
     /// Test EOF error
     #[test]
     fn test_eof_error() {
@@ -0,0 +1,264 @@
+//! Delta encoding of repeated messages: only the fields which changed
+//! from a previous value are sent, preceded by a [`PresenceMask`] of
+//! the changed fields.
+//!
+//! The decoder applies the changed fields onto its copy of the previous
+//! value, so both sides have to agree on it.
+
+use core::fmt;
+
+use serde::{
+    de::{self, SeqAccess},
+    ser::SerializeTuple,
+    Deserializer, Serializer,
+};
+
+use crate::PresenceMask;
+
+/// Field-by-field comparison and (de)serialization of a message, with at
+/// most 32 fields, each identified by a bit of the [`PresenceMask`].
+///
+/// ```
+/// use serde::{de::SeqAccess, ser::SerializeTuple};
+/// use ssh_format::{delta::Delta, PresenceMask};
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Status {
+///     sessions: u32,
+///     name: String,
+/// }
+///
+/// impl Delta for Status {
+///     fn changed(&self, base: &Self) -> PresenceMask {
+///         PresenceMask::new()
+///             .with(0, self.sessions != base.sessions)
+///             .with(1, self.name != base.name)
+///     }
+///
+///     fn serialize_fields<S: SerializeTuple>(
+///         &self,
+///         mask: PresenceMask,
+///         tuple: &mut S,
+///     ) -> Result<(), S::Error> {
+///         if mask.is_set(0) {
+///             tuple.serialize_element(&self.sessions)?;
+///         }
+///         if mask.is_set(1) {
+///             tuple.serialize_element(&self.name)?;
+///         }
+///         Ok(())
+///     }
+///
+///     fn deserialize_fields<'de, A: SeqAccess<'de>>(
+///         &mut self,
+///         mask: PresenceMask,
+///         seq: &mut A,
+///     ) -> Result<(), A::Error> {
+///         if let Some(sessions) = mask.deserialize_field(seq, 0)? {
+///             self.sessions = sessions;
+///         }
+///         if let Some(name) = mask.deserialize_field(seq, 1)? {
+///             self.name = name;
+///         }
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait Delta {
+    /// Return the mask of fields of `self` which differ from `base`.
+    fn changed(&self, base: &Self) -> PresenceMask;
+
+    /// Serialize the fields whose bit is set in `mask`, in order.
+    fn serialize_fields<S: SerializeTuple>(
+        &self,
+        mask: PresenceMask,
+        tuple: &mut S,
+    ) -> Result<(), S::Error>;
+
+    /// Deserialize the fields whose bit is set in `mask`, in order, and
+    /// assign them to `self`.
+    fn deserialize_fields<'de, A: SeqAccess<'de>>(
+        &mut self,
+        mask: PresenceMask,
+        seq: &mut A,
+    ) -> Result<(), A::Error>;
+}
+
+/// Serialize the mask of fields of `value` which differ from `base`,
+/// followed by these fields.
+pub fn serialize_delta<S, T>(base: &T, value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Delta,
+{
+    let mask = value.changed(base);
+
+    let mut tuple = serializer.serialize_tuple(1 + mask.bits().count_ones() as usize)?;
+    tuple.serialize_element(&mask)?;
+    value.serialize_fields(mask, &mut tuple)?;
+    tuple.end()
+}
+
+/// Deserialize a delta written by [`serialize_delta`] and return `base`
+/// with the changed fields applied.
+pub fn deserialize_delta<'de, D, T>(base: &T, deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Delta + Clone,
+{
+    struct DeltaVisitor<T>(T);
+
+    impl<'de, T: Delta> de::Visitor<'de> for DeltaVisitor<T> {
+        type Value = T;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a mask of changed fields followed by these fields")
+        }
+
+        fn visit_seq<A>(mut self, mut seq: A) -> Result<T, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mask: PresenceMask = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+
+            self.0.deserialize_fields(mask, &mut seq)?;
+            Ok(self.0)
+        }
+    }
+
+    deserializer.deserialize_tuple(usize::MAX, DeltaVisitor(base.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{de::SeqAccess, ser::SerializeTuple};
+
+    use super::*;
+    use crate::{Deserializer as SshDeserializer, Error, Serializer as SshSerializer};
+
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    struct State {
+        sessions: u32,
+        name: String,
+        flags: u8,
+    }
+
+    impl Delta for State {
+        fn changed(&self, base: &Self) -> PresenceMask {
+            PresenceMask::new()
+                .with(0, self.sessions != base.sessions)
+                .with(1, self.name != base.name)
+                .with(2, self.flags != base.flags)
+        }
+
+        fn serialize_fields<S: SerializeTuple>(
+            &self,
+            mask: PresenceMask,
+            tuple: &mut S,
+        ) -> Result<(), S::Error> {
+            if mask.is_set(0) {
+                tuple.serialize_element(&self.sessions)?;
+            }
+            if mask.is_set(1) {
+                tuple.serialize_element(&self.name)?;
+            }
+            if mask.is_set(2) {
+                tuple.serialize_element(&self.flags)?;
+            }
+            Ok(())
+        }
+
+        fn deserialize_fields<'de, A: SeqAccess<'de>>(
+            &mut self,
+            mask: PresenceMask,
+            seq: &mut A,
+        ) -> Result<(), A::Error> {
+            if let Some(sessions) = mask.deserialize_field(seq, 0)? {
+                self.sessions = sessions;
+            }
+            if let Some(name) = mask.deserialize_field(seq, 1)? {
+                self.name = name;
+            }
+            if let Some(flags) = mask.deserialize_field(seq, 2)? {
+                self.flags = flags;
+            }
+            Ok(())
+        }
+    }
+
+    fn serialize(base: &State, value: &State) -> Vec<u8> {
+        let mut serializer: SshSerializer = SshSerializer::default();
+        serialize_delta(base, value, &mut serializer).unwrap();
+        serializer.output
+    }
+
+    #[test]
+    fn test_delta() {
+        let base = State {
+            sessions: 1,
+            name: "host".to_owned(),
+            flags: 0,
+        };
+
+        let current = State {
+            sessions: 2,
+            flags: 0x80,
+            ..base.clone()
+        };
+
+        let serialized = serialize(&base, &current);
+        assert_eq!(serialized, [0, 0, 0, 0b101, 0, 0, 0, 2, 0x80]);
+
+        let mut deserializer = SshDeserializer::from_bytes(&serialized);
+        assert_eq!(
+            deserialize_delta(&base, &mut deserializer).unwrap(),
+            current
+        );
+        assert!(!deserializer.has_remaining_data());
+
+        for chunk_size in 1..serialized.len() {
+            let mut deserializer = SshDeserializer::new(serialized.chunks(chunk_size).fuse());
+            assert_eq!(
+                deserialize_delta(&base, &mut deserializer).unwrap(),
+                current
+            );
+        }
+    }
+
+    #[test]
+    fn test_unchanged() {
+        let base = State {
+            sessions: 1,
+            name: "host".to_owned(),
+            flags: 0,
+        };
+
+        let serialized = serialize(&base, &base);
+        assert_eq!(serialized, [0, 0, 0, 0]);
+
+        let mut deserializer = SshDeserializer::from_bytes(&serialized);
+        assert_eq!(deserialize_delta(&base, &mut deserializer).unwrap(), base);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let base = State {
+            sessions: 1,
+            name: "host".to_owned(),
+            flags: 0,
+        };
+        let current = State {
+            name: "other".to_owned(),
+            ..base.clone()
+        };
+
+        let serialized = serialize(&base, &current);
+        let mut deserializer = SshDeserializer::from_bytes(&serialized[..serialized.len() - 1]);
+        assert!(matches!(
+            deserialize_delta(&base, &mut deserializer),
+            Err(Error::Eof)
+        ));
+    }
+}
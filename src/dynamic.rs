@@ -0,0 +1,268 @@
+//! Schema-driven encoding and decoding of messages without compile-time
+//! types, e.g. for protocol inspectors or test harnesses.
+//!
+//! Since the format is not self-describing, the expected sequence of
+//! fields has to be supplied as a slice of [`FieldType`] for decoding.
+
+use alloc::{string::String, vec, vec::Vec};
+use core::convert::TryInto;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{split_header, Error, Result};
+
+/// Type of a field, each encoded as its Rust counterpart would be.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum FieldType {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    I32,
+    I64,
+    Char,
+    String,
+    Bytes,
+}
+
+/// Value of a field decoded according to its [`FieldType`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum FieldValue {
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I32(i32),
+    I64(i64),
+    Char(char),
+    String(String),
+    Bytes(Vec<u8>),
+}
+
+impl FieldValue {
+    pub fn field_type(&self) -> FieldType {
+        match self {
+            FieldValue::Bool(_) => FieldType::Bool,
+            FieldValue::U8(_) => FieldType::U8,
+            FieldValue::U16(_) => FieldType::U16,
+            FieldValue::U32(_) => FieldType::U32,
+            FieldValue::U64(_) => FieldType::U64,
+            FieldValue::I32(_) => FieldType::I32,
+            FieldValue::I64(_) => FieldType::I64,
+            FieldValue::Char(_) => FieldType::Char,
+            FieldValue::String(_) => FieldType::String,
+            FieldValue::Bytes(_) => FieldType::Bytes,
+        }
+    }
+}
+
+impl Serialize for FieldValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        match self {
+            FieldValue::Bool(v) => serializer.serialize_bool(*v),
+            FieldValue::U8(v) => serializer.serialize_u8(*v),
+            FieldValue::U16(v) => serializer.serialize_u16(*v),
+            FieldValue::U32(v) => serializer.serialize_u32(*v),
+            FieldValue::U64(v) => serializer.serialize_u64(*v),
+            FieldValue::I32(v) => serializer.serialize_i32(*v),
+            FieldValue::I64(v) => serializer.serialize_i64(*v),
+            FieldValue::Char(v) => serializer.serialize_char(*v),
+            FieldValue::String(v) => serializer.serialize_str(v),
+            FieldValue::Bytes(v) => serializer.serialize_bytes(v),
+        }
+    }
+}
+
+/// Encode `values` into a message with the first 4 bytes representing the
+/// size of the rest of it, same as [`crate::to_bytes`].
+///
+/// Values which would not be decoded back as-is by [`decode_with_schema`]
+/// are rejected, i.e. strings containing NUL bytes fail with
+/// `Error::Unsupported(&"NUL byte in canonical mode")`.
+pub fn encode_with_schema(values: &[FieldValue]) -> Result<Vec<u8>> {
+    let mut buffer = vec![0, 0, 0, 0];
+
+    let mut serializer = crate::Serializer::new(&mut buffer).canonical(true);
+    values
+        .iter()
+        .try_for_each(|value| value.serialize(&mut serializer))?;
+    let header = serializer.create_header(0)?;
+
+    buffer[..4].copy_from_slice(&header);
+
+    Ok(buffer)
+}
+
+fn decode_field<'de, D>(
+    deserializer: D,
+    field_type: FieldType,
+) -> core::result::Result<FieldValue, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match field_type {
+        FieldType::Bool => FieldValue::Bool(Deserialize::deserialize(deserializer)?),
+        FieldType::U8 => FieldValue::U8(Deserialize::deserialize(deserializer)?),
+        FieldType::U16 => FieldValue::U16(Deserialize::deserialize(deserializer)?),
+        FieldType::U32 => FieldValue::U32(Deserialize::deserialize(deserializer)?),
+        FieldType::U64 => FieldValue::U64(Deserialize::deserialize(deserializer)?),
+        FieldType::I32 => FieldValue::I32(Deserialize::deserialize(deserializer)?),
+        FieldType::I64 => FieldValue::I64(Deserialize::deserialize(deserializer)?),
+        FieldType::Char => FieldValue::Char(Deserialize::deserialize(deserializer)?),
+        FieldType::String => FieldValue::String(Deserialize::deserialize(deserializer)?),
+        FieldType::Bytes => FieldValue::Bytes(Deserialize::deserialize(deserializer)?),
+    })
+}
+
+/// Decode a message written by e.g. [`crate::to_bytes`], including its
+/// 4-byte header, into one [`FieldValue`] per entry of `schema`.
+///
+/// Return [`Error::Eof`] if the message is shorter than described by
+/// `schema`, or [`Error::InvalidLength`] if its header does not match
+/// its length or if bytes are left after the last field.
+pub fn decode_with_schema(buf: &[u8], schema: &[FieldType]) -> Result<Vec<FieldValue>> {
+    let (header, body) = split_header(buf)?;
+    let expected: usize = header.len().try_into().map_err(|_| Error::TooLong)?;
+
+    if expected != body.len() {
+        return Err(Error::InvalidLength {
+            expected,
+            found: body.len(),
+        });
+    }
+
+    let mut deserializer = crate::Deserializer::from_bytes(body);
+    let values = schema
+        .iter()
+        .map(|field_type| decode_field(&mut deserializer, *field_type))
+        .collect::<Result<Vec<_>>>()?;
+
+    let (trailing, _) = deserializer.into_inner();
+    if !trailing.is_empty() {
+        return Err(Error::InvalidLength {
+            expected: body.len() - trailing.len(),
+            found: body.len(),
+        });
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::to_bytes;
+
+    fn all_types() -> (Vec<FieldType>, Vec<FieldValue>, Vec<u8>) {
+        let value = (
+            true,
+            0x12_u8,
+            0x1234_u16,
+            0x12345678_u32,
+            u64::MAX,
+            -1_i32,
+            i64::MIN,
+            '\u{1F980}',
+            "Hello",
+            vec![0x00_u8, 0xff],
+        );
+        let schema = vec![
+            FieldType::Bool,
+            FieldType::U8,
+            FieldType::U16,
+            FieldType::U32,
+            FieldType::U64,
+            FieldType::I32,
+            FieldType::I64,
+            FieldType::Char,
+            FieldType::String,
+            FieldType::Bytes,
+        ];
+        let values = vec![
+            FieldValue::Bool(true),
+            FieldValue::U8(0x12),
+            FieldValue::U16(0x1234),
+            FieldValue::U32(0x12345678),
+            FieldValue::U64(u64::MAX),
+            FieldValue::I32(-1),
+            FieldValue::I64(i64::MIN),
+            FieldValue::Char('\u{1F980}'),
+            FieldValue::String("Hello".to_owned()),
+            FieldValue::Bytes(vec![0x00, 0xff]),
+        ];
+        (schema, values, to_bytes(&value).unwrap())
+    }
+
+    #[test]
+    fn test_decode() {
+        let (schema, values, serialized) = all_types();
+
+        let decoded = decode_with_schema(&serialized, &schema).unwrap();
+        assert_eq!(decoded, values);
+        assert!(decoded
+            .iter()
+            .zip(&schema)
+            .all(|(value, field_type)| value.field_type() == *field_type));
+    }
+
+    #[test]
+    fn test_encode() {
+        let (schema, values, serialized) = all_types();
+
+        let encoded = encode_with_schema(&values).unwrap();
+        assert_eq!(encoded, serialized);
+        assert_eq!(decode_with_schema(&encoded, &schema).unwrap(), values);
+
+        for value in values {
+            let encoded = encode_with_schema(std::slice::from_ref(&value)).unwrap();
+            assert_eq!(
+                decode_with_schema(&encoded, &[value.field_type()]).unwrap(),
+                [value]
+            );
+        }
+
+        assert_eq!(encode_with_schema(&[]).unwrap(), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_encode_unsupported() {
+        assert!(matches!(
+            encode_with_schema(&[FieldValue::U8(1), FieldValue::String("a\0b".to_owned())]),
+            Err(Error::Unsupported(&"NUL byte in canonical mode"))
+        ));
+    }
+
+    #[test]
+    fn test_mismatch() {
+        let (mut schema, _, serialized) = all_types();
+
+        // Fewer fields than described
+        schema.push(FieldType::U8);
+        assert!(matches!(
+            decode_with_schema(&serialized, &schema),
+            Err(Error::Eof)
+        ));
+
+        // More fields than described
+        schema.truncate(2);
+        assert!(matches!(
+            decode_with_schema(&serialized, &schema),
+            Err(Error::InvalidLength { expected: 5, found }) if found == serialized.len() - 4
+        ));
+
+        // Truncated message
+        assert!(matches!(
+            decode_with_schema(&serialized[..serialized.len() - 1], &schema),
+            Err(Error::InvalidLength { .. })
+        ));
+
+        // Invalid bool
+        let serialized = to_bytes(&2_u32).unwrap();
+        assert!(matches!(
+            decode_with_schema(&serialized, &[FieldType::Bool]),
+            Err(Error::InvalidBoolEncoding)
+        ));
+    }
+}
@@ -0,0 +1,192 @@
+//! Serialize and deserialize [`Either`] as discriminant(`u32`) + content,
+//! for responses that are one of two shapes distinguished by a leading
+//! `u32`.
+//!
+//! Since the discriminants are parameters, wrap these functions to use
+//! them with `#[serde(serialize_with = "...", deserialize_with = "...")]`.
+
+use core::{fmt, marker::PhantomData};
+
+use either::Either;
+use serde::{
+    de::{self, SeqAccess, Unexpected},
+    ser::SerializeTuple,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// Write `disc_a` followed by `A`, or `disc_b` followed by `B`.
+pub fn serialize_either<S, A, B>(
+    value: &Either<A, B>,
+    disc_a: u32,
+    disc_b: u32,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    A: Serialize,
+    B: Serialize,
+{
+    let mut tuple = serializer.serialize_tuple(2)?;
+    match value {
+        Either::Left(a) => {
+            tuple.serialize_element(&disc_a)?;
+            tuple.serialize_element(a)?;
+        }
+        Either::Right(b) => {
+            tuple.serialize_element(&disc_b)?;
+            tuple.serialize_element(b)?;
+        }
+    }
+    tuple.end()
+}
+
+/// Read the leading `u32`, then deserialize `A` if it is `disc_a` or `B`
+/// if it is `disc_b`.
+///
+/// Any other discriminant is rejected with an error.
+pub fn deserialize_either<'de, D, A, B>(
+    deserializer: D,
+    disc_a: u32,
+    disc_b: u32,
+) -> Result<Either<A, B>, D::Error>
+where
+    D: Deserializer<'de>,
+    A: Deserialize<'de>,
+    B: Deserialize<'de>,
+{
+    struct EitherVisitor<A, B> {
+        disc_a: u32,
+        disc_b: u32,
+        phantom: PhantomData<(A, B)>,
+    }
+
+    impl<'de, A, B> de::Visitor<'de> for EitherVisitor<A, B>
+    where
+        A: Deserialize<'de>,
+        B: Deserialize<'de>,
+    {
+        type Value = Either<A, B>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                formatter,
+                "discriminant {} or {} followed by its content",
+                self.disc_a, self.disc_b
+            )
+        }
+
+        fn visit_seq<V>(self, mut seq: V) -> Result<Either<A, B>, V::Error>
+        where
+            V: SeqAccess<'de>,
+        {
+            let disc: u32 = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+
+            let value = if disc == self.disc_a {
+                seq.next_element()?.map(Either::Left)
+            } else if disc == self.disc_b {
+                seq.next_element()?.map(Either::Right)
+            } else {
+                return Err(de::Error::invalid_value(
+                    Unexpected::Unsigned(disc.into()),
+                    &self,
+                ));
+            };
+
+            value.ok_or_else(|| de::Error::invalid_length(1, &self))
+        }
+    }
+
+    deserializer.deserialize_tuple(
+        2,
+        EitherVisitor {
+            disc_a,
+            disc_b,
+            phantom: PhantomData,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use either::Either;
+    use serde::{Deserialize, Serialize};
+
+    use super::{deserialize_either, serialize_either};
+    use crate::{Deserializer, Error, Serializer};
+
+    const STATUS_OK: u32 = 0x8000_0001;
+    const STATUS_FAILURE: u32 = 0x8000_0003;
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Success {
+        request_id: u32,
+    }
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Failure {
+        request_id: u32,
+        reason: String,
+    }
+
+    type Response = Either<Success, Failure>;
+
+    fn serialize(response: &Response) -> Vec<u8> {
+        let mut serializer = Serializer::new(Vec::new());
+        serialize_either(response, STATUS_OK, STATUS_FAILURE, &mut serializer).unwrap();
+        serializer.output
+    }
+
+    fn test_roundtrip(response: Response, expected_disc: u32) {
+        let serialized = serialize(&response);
+        assert_eq!(serialized[..4], expected_disc.to_be_bytes());
+
+        let mut deserializer = Deserializer::from_bytes(&serialized);
+        assert_eq!(
+            deserialize_either::<_, Success, Failure>(&mut deserializer, STATUS_OK, STATUS_FAILURE)
+                .unwrap(),
+            response
+        );
+        assert!(!deserializer.has_remaining_data());
+
+        for chunk_size in 1..serialized.len() {
+            let mut deserializer = Deserializer::new(serialized.chunks(chunk_size).fuse());
+            assert_eq!(
+                deserialize_either::<_, Success, Failure>(
+                    &mut deserializer,
+                    STATUS_OK,
+                    STATUS_FAILURE
+                )
+                .unwrap(),
+                response
+            );
+        }
+    }
+
+    #[test]
+    fn test_left() {
+        test_roundtrip(Either::Left(Success { request_id: 1 }), STATUS_OK);
+    }
+
+    #[test]
+    fn test_right() {
+        test_roundtrip(
+            Either::Right(Failure {
+                request_id: 2,
+                reason: "Permission denied".to_owned(),
+            }),
+            STATUS_FAILURE,
+        );
+    }
+
+    #[test]
+    fn test_unknown_discriminant() {
+        let serialized = crate::to_bytes(&(0x8000_0002_u32, 1_u32)).unwrap();
+        let mut deserializer = Deserializer::from_bytes(&serialized[4..]);
+        assert!(matches!(
+            deserialize_either::<_, Success, Failure>(&mut deserializer, STATUS_OK, STATUS_FAILURE),
+            Err(Error::Message(_))
+        ));
+    }
+}
@@ -0,0 +1,15 @@
+/// Byte order of the integers written by [`crate::Serializer`] and read by
+/// [`crate::Deserializer`], including lengths, `bool`, `char` and variant
+/// indices.
+///
+/// openssh uses [`Endianness::Big`], which is the default.
+///
+/// Sub-messages written by [`crate::framed`] are the exception: only their
+/// length follows the configured byte order, their content is always big
+/// endian.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum Endianness {
+    #[default]
+    Big,
+    Little,
+}
@@ -0,0 +1,158 @@
+//! Encode a string as exactly `N` bytes, NUL-padded and without length,
+//! like a C fixed-size `char` array.
+//!
+//! Serializing a string longer than `N` bytes is an error, and trailing
+//! NULs are trimmed on deserialization.
+//!
+//! Since `N` is a const parameter, it has to be specified on use:
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Entry {
+//!     #[serde(
+//!         serialize_with = "ssh_format::fixed_str::serialize::<_, 16>",
+//!         deserialize_with = "ssh_format::fixed_str::deserialize::<_, 16>"
+//!     )]
+//!     name: String,
+//! }
+//! ```
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+use serde::{
+    de::{self, SeqAccess},
+    ser::{self, SerializeTuple},
+    Deserializer, Serializer,
+};
+
+pub fn serialize<S, const N: usize>(value: &str, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let bytes = value.as_bytes();
+    if bytes.len() > N {
+        return Err(ser::Error::custom(format_args!(
+            "string of {} bytes does not fit in {} bytes",
+            bytes.len(),
+            N
+        )));
+    }
+
+    let mut tuple = serializer.serialize_tuple(N)?;
+    for byte in bytes {
+        tuple.serialize_element(byte)?;
+    }
+    for _ in bytes.len()..N {
+        tuple.serialize_element(&0_u8)?;
+    }
+    tuple.end()
+}
+
+pub fn deserialize<'de, D, const N: usize>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct FixedStrVisitor<const N: usize>;
+
+    impl<'de, const N: usize> de::Visitor<'de> for FixedStrVisitor<N> {
+        type Value = String;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(formatter, "a NUL-padded string of {} bytes", N)
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<String, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut bytes = Vec::with_capacity(N);
+            for i in 0..N {
+                let byte: u8 = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+                bytes.push(byte);
+            }
+
+            let len = bytes
+                .iter()
+                .rposition(|byte| *byte != 0)
+                .map_or(0, |i| i + 1);
+            bytes.truncate(len);
+
+            String::from_utf8(bytes).map_err(de::Error::custom)
+        }
+    }
+
+    deserializer.deserialize_tuple(N, FixedStrVisitor::<N>)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{from_bytes, to_bytes, Deserializer, Error};
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Entry {
+        id: u32,
+        #[serde(
+            serialize_with = "crate::fixed_str::serialize::<_, 8>",
+            deserialize_with = "crate::fixed_str::deserialize::<_, 8>"
+        )]
+        name: String,
+        flags: u8,
+    }
+
+    fn test_roundtrip(name: &str, encoded: &[u8; 8]) {
+        let entry = Entry {
+            id: 1,
+            name: name.to_owned(),
+            flags: 0xff,
+        };
+
+        let serialized = to_bytes(&entry).unwrap();
+        let body = &serialized[4..];
+        assert_eq!(body.len(), 4 + 8 + 1);
+        assert_eq!(body[4..12], *encoded);
+
+        assert_eq!(from_bytes::<Entry>(body).unwrap().0, entry);
+
+        for chunk_size in 1..body.len() {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+            assert_eq!(Entry::deserialize(&mut deserializer).unwrap(), entry);
+        }
+    }
+
+    #[test]
+    fn test_padded() {
+        test_roundtrip("eth0", b"eth0\0\0\0\0");
+        test_roundtrip("", &[0; 8]);
+    }
+
+    #[test]
+    fn test_exact() {
+        test_roundtrip("12345678", b"12345678");
+    }
+
+    #[test]
+    fn test_too_long() {
+        let entry = Entry {
+            id: 1,
+            name: "123456789".to_owned(),
+            flags: 0,
+        };
+        assert!(matches!(to_bytes(&entry), Err(Error::Message(_))));
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let serialized = to_bytes(&(1_u32, [0xff_u8; 8], 0_u8)).unwrap();
+        assert!(matches!(
+            from_bytes::<Entry>(&serialized[4..]),
+            Err(Error::Message(_))
+        ));
+    }
+}
@@ -0,0 +1,160 @@
+//! Encode a value as length(`u32`) + the value encoded as-is, i.e. as a
+//! self-delimited sub-message.
+//!
+//! On deserialization, the value must consume exactly the declared length,
+//! otherwise an error is returned. This is handy for enums, which would
+//! then be encoded as length(`u32`) + index(`u32`) + content.
+//!
+//! Use it with `#[serde(with = "ssh_format::framed")]`.
+//!
+//! The sub-message is always encoded with the default settings, i.e. big
+//! endian and neither strict nor canonical, whatever the settings of the
+//! outer [`crate::Serializer`] or [`crate::Deserializer`]. Only its length
+//! follows the outer [`crate::Endianness`].
+
+use core::{fmt, marker::PhantomData};
+
+use serde::{de, de::DeserializeOwned, ser, Deserializer, Serialize, Serializer};
+
+use crate::{from_bytes, Error};
+
+pub fn serialize<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: ?Sized + Serialize,
+{
+    let mut inner: crate::Serializer = crate::Serializer::default();
+    value.serialize(&mut inner).map_err(ser::Error::custom)?;
+
+    serializer.serialize_bytes(&inner.output)
+}
+
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    struct FramedVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: DeserializeOwned> de::Visitor<'de> for FramedVisitor<T> {
+        type Value = T;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a length-prefixed sub-message")
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<T, E>
+        where
+            E: de::Error,
+        {
+            let (t, trailing) = from_bytes::<T>(v).map_err(E::custom)?;
+
+            if trailing.is_empty() {
+                Ok(t)
+            } else {
+                Err(E::custom(Error::InvalidLength {
+                    expected: v.len(),
+                    found: v.len() - trailing.len(),
+                }))
+            }
+        }
+    }
+
+    deserializer.deserialize_bytes(FramedVisitor(PhantomData))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{from_bytes, to_bytes, Deserializer, Error};
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    enum Forward {
+        Local { port: u32 },
+        Stream { path: String, mode: u16 },
+    }
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Request {
+        request_id: u32,
+        #[serde(with = "crate::framed")]
+        forward: Forward,
+        trailer: u8,
+    }
+
+    fn test_roundtrip(forward: Forward, expected: &[u8]) {
+        let request = Request {
+            request_id: 0x12,
+            forward,
+            trailer: 0xff,
+        };
+
+        let serialized = to_bytes(&request).unwrap();
+        let body = &serialized[4..];
+        assert_eq!(body[..4], [0, 0, 0, 0x12]);
+        assert_eq!(body[4..8], (expected.len() as u32).to_be_bytes());
+        assert_eq!(&body[8..body.len() - 1], expected);
+
+        assert_eq!(from_bytes::<Request>(body).unwrap().0, request);
+
+        for chunk_size in 1..body.len() {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+            assert_eq!(Request::deserialize(&mut deserializer).unwrap(), request);
+        }
+    }
+
+    #[test]
+    fn test_enum() {
+        test_roundtrip(Forward::Local { port: 22 }, &[0, 0, 0, 0, 0, 0, 0, 22]);
+        test_roundtrip(
+            Forward::Stream {
+                path: "/tmp/sock".to_owned(),
+                mode: 0o600,
+            },
+            b"\0\0\0\x01\0\0\0\x09/tmp/sock\x01\x80",
+        );
+    }
+
+    #[test]
+    fn test_little_endian() {
+        use crate::{Endianness, Serializer};
+
+        let request = Request {
+            request_id: 0x12,
+            forward: Forward::Local { port: 22 },
+            trailer: 0xff,
+        };
+
+        let mut serializer = Serializer::new(Vec::new()).endianness(Endianness::Little);
+        request.serialize(&mut serializer).unwrap();
+
+        // The length is little endian but the sub-message is big endian
+        let body = serializer.output;
+        assert_eq!(body[..4], [0x12, 0, 0, 0]);
+        assert_eq!(body[4..8], [8, 0, 0, 0]);
+        assert_eq!(body[8..16], [0, 0, 0, 0, 0, 0, 0, 22]);
+        assert_eq!(body[16..], [0xff]);
+
+        let mut deserializer = Deserializer::from_bytes(&body).endianness(Endianness::Little);
+        assert_eq!(Request::deserialize(&mut deserializer).unwrap(), request);
+    }
+
+    #[test]
+    fn test_length_mismatch() {
+        // Sub-message longer than the variant
+        let serialized =
+            to_bytes(&(0x12_u32, &[0_u8, 0, 0, 0, 0, 0, 0, 22, 0][..], 0xff_u8)).unwrap();
+        assert!(matches!(
+            from_bytes::<Request>(&serialized[4..]),
+            Err(Error::Message(_))
+        ));
+
+        // Sub-message shorter than the variant
+        let serialized = to_bytes(&(0x12_u32, &[0_u8, 0, 0, 0, 0, 0, 0][..], 0xff_u8)).unwrap();
+        assert!(matches!(
+            from_bytes::<Request>(&serialized[4..]),
+            Err(Error::Message(_))
+        ));
+    }
+}
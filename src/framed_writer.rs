@@ -0,0 +1,117 @@
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::{Result, Serializer};
+
+/// Write framed messages into `W`, reusing an internal buffer across them.
+///
+/// Each message is written as the 4-byte size of the rest of the message
+/// followed by its body, same as [`crate::to_bytes`].
+#[derive(Debug)]
+pub struct FramedWriter<W> {
+    writer: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> FramedWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Serialize `value` and write the whole frame into the writer.
+    ///
+    /// Nothing is written if serialization fails.
+    pub fn send<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let result = self.send_inner(value);
+        self.buffer.clear();
+        result
+    }
+
+    fn send_inner<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.buffer.extend_from_slice(&[0, 0, 0, 0]);
+
+        let mut serializer = Serializer::new(&mut self.buffer);
+        value.serialize(&mut serializer)?;
+        let header = serializer.create_header(0)?;
+
+        self.buffer[..4].copy_from_slice(&header);
+        self.writer.write_all(&self.buffer)?;
+
+        Ok(())
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<W> FramedWriter<W> {
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::ser;
+
+    use super::*;
+    use crate::{decode_all, Error};
+
+    #[test]
+    fn test_send() {
+        let mut writer = FramedWriter::new(Vec::new());
+
+        writer.send(&(1_u32, "Hello")).unwrap();
+        writer.send(&(2_u32, "world!")).unwrap();
+        writer.flush().unwrap();
+
+        let written = writer.into_inner();
+        let frames: Vec<(u32, &str)> = decode_all(&written).collect::<Result<_>>().unwrap();
+        assert_eq!(frames, [(1, "Hello"), (2, "world!")]);
+    }
+
+    #[test]
+    fn test_send_error() {
+        struct Invalid;
+
+        impl Serialize for Invalid {
+            fn serialize<S: ser::Serializer>(
+                &self,
+                _serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                Err(ser::Error::custom("invalid"))
+            }
+        }
+
+        let mut writer = FramedWriter::new(Vec::new());
+
+        assert!(matches!(
+            writer.send(&(1_u32, Invalid)),
+            Err(Error::Message(_))
+        ));
+        assert!(writer.get_ref().is_empty());
+
+        writer.send(&3_u8).unwrap();
+        assert_eq!(writer.get_ref()[..], [0, 0, 0, 1, 3]);
+    }
+}
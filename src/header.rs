@@ -0,0 +1,81 @@
+use core::convert::TryInto;
+
+use crate::{Error, Result};
+
+/// The 4-byte header preceding a serialized message, which is the length
+/// of the message body in big endian.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Header(u32);
+
+impl Header {
+    pub const fn new(len: u32) -> Self {
+        Self(len)
+    }
+
+    pub const fn from_bytes(bytes: [u8; 4]) -> Self {
+        Self(u32::from_be_bytes(bytes))
+    }
+
+    pub const fn to_bytes(self) -> [u8; 4] {
+        self.0.to_be_bytes()
+    }
+
+    /// Length of the message body.
+    pub const fn len(self) -> u32 {
+        self.0
+    }
+
+    /// Whether the message body is empty.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+/// Split `buf` into its [`Header`] and the bytes following it.
+///
+/// Return [`Error::Eof`] if `buf` is shorter than 4 bytes. The returned
+/// bytes are not checked against [`Header::len`].
+pub fn split_header(buf: &[u8]) -> Result<(Header, &[u8])> {
+    if buf.len() < 4 {
+        return Err(Error::Eof);
+    }
+    let (header, body) = buf.split_at(4);
+
+    Ok((Header::from_bytes(header.try_into().unwrap()), body))
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+    use crate::to_bytes;
+
+    #[test]
+    fn test_header() {
+        let header = Header::from_bytes([0x12, 0x34, 0x56, 0x78]);
+        assert_eq!(header.len(), 0x12345678);
+        assert_eq!(header.to_bytes(), [0x12, 0x34, 0x56, 0x78]);
+        assert_eq!(Header::new(0x12345678), header);
+    }
+
+    #[test]
+    fn test_split_header() {
+        let serialized = to_bytes(&(0x1234_u16, "Hello")).unwrap();
+
+        let (header, body) = split_header(&serialized).unwrap();
+        assert_eq!(header.len() as usize, body.len());
+        assert_eq!(body, &serialized[4..]);
+
+        let (header, body) = split_header(&[0, 0, 0, 0]).unwrap();
+        assert!(header.is_empty());
+        assert!(body.is_empty());
+    }
+
+    #[test]
+    fn test_split_header_short() {
+        for len in 0..4 {
+            assert_matches!(split_header(&[0; 3][..len]), Err(Error::Eof));
+        }
+    }
+}
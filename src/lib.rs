@@ -1,34 +1,126 @@
 //! Data format used to communicate with openssh mux server.
 //!
 //! Format details:
-//!  - All integers are encoded in big endian;
+//!  - All integers are encoded in big endian, unless configured otherwise with
+//!    `Serializer::endianness` and `Deserializer::endianness` for non-openssh peers;
 //!  - Boolean are encoded as `u32` according to [here][1];
 //!  - `char` are encoded as `u32`;
 //!  - Strings and bytes are encoded as length(`u32`) + content, same as [`sshbuf_put_string`];
 //!  - `Option::None` are omitted while `Option::Some(v)` has the same encoding as `v` since
-//!    openssh mux protocol allows optional parameter at the end of the message;
-//!  - struct/tuple are encoded as-is, unit struct/tuple are omitted;
-//!  - sequence are encoded as if it is a tuple according to [here][0], thus it cannot be
-//!    deserialized;
+//!    openssh mux protocol allows optional parameter at the end of the message,
+//!    thus on deserialization `Option` is `Some` only if there is remaining data;
+//!  - struct/tuple/fixed-size array are encoded as-is without length, unit struct/tuple
+//!    are omitted;
+//!  - sequence are encoded as length(`u32`) + elements encoded as-is, sequence with unknown
+//!    length is unsupported;
 //!  - Variant is encoded as index(`u32`) + content encoded as-is (it is expected to
 //!    manually implement `Serialize` and `Deserialize` to ensure the `variant_index`
 //!    is the one you expected);
-//!  - Serializing/Deserializing map is unsupported;
+//!  - `Result` is encoded as a variant, with `Ok` being index 0 and `Err` being index 1;
+//!  - `serde::de::IgnoredAny` skips a string or bytes, i.e. length(`u32`) + content, on
+//!    deserialization, since the format is not self-describing;
+//!  - Map is only supported with feature `map` (enabled by default), and is encoded
+//!    as length(`u32`) + key-value pairs;
 //!
 //! [`sshbuf_put_string`]: https://github.com/openssh/openssh-portable/blob/2dc328023f60212cd29504fc05d849133ae47355/sshbuf-getput-basic.c#L514
-//! [0]: https://github.com/openssh/openssh-portable/blob/19b3d846f06697c85957ab79a63454f57f8e22d6/mux.c#L1906
 //! [1]: https://github.com/openssh/openssh-portable/blob/19b3d846f06697c85957ab79a63454f57f8e22d6/mux.c#L1897
 //! ## Feature
+//!  - `std` (default) enables `io`-based APIs: `to_writer`, `from_reader`, `FramedWriter`,
+//!    `Error::IoError` and conversions from/to `std::io::Error`, and `systemtime_unix_*`.
+//!    Without it, the crate is `no_std` and only requires `alloc`.
 //!  - `is_human_readable` enables `Serializer::is_human_readable` and
 //!    `Deserializer::is_human_readable`.
+//!  - `map` (default) enables (de)serializing maps with known length as length(`u32`) +
+//!    key-value pairs.
+//!  - `bytes` implements `SerOutput` for `bytes::BytesMut` and enables `bytes_field`
+//!    for (de)serializing `bytes::Bytes` fields, and `Deserializer::from_bytes_chunks`
+//!    for reading them without copying.
+//!  - `either` enables `either_field` for (de)serializing `either::Either` by discriminant.
+//!  - `varint-len` enables `to_bytes_varint` and `from_bytes_varint`, which encode the
+//!    length prefix as a LEB128 varint instead of `u32`.
+//!  - `uuid` enables `uuid_bytes` for (de)serializing `uuid::Uuid` as 16 raw bytes.
+//!  - `smallvec` implements `SerOutput` for `smallvec::SmallVec`, e.g. to serialize small
+//!    messages into a stack-allocated buffer.
+//!  - `tokio-codec` enables `SshFormatCodec`, a `tokio_util::codec` encoder and decoder
+//!    of frames with the 4-byte size.
+//!  - `tokio` enables `read_frame`, reading a single frame with the 4-byte size from a
+//!    `tokio::io::AsyncRead`.
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![forbid(unsafe_code)]
 
+extern crate alloc;
+
+#[cfg(feature = "tokio")]
+mod async_read;
+#[cfg(feature = "bytes")]
+pub mod bytes_field;
+#[cfg(feature = "tokio-codec")]
+mod codec;
+mod crc;
 mod de;
+pub mod delta;
+pub mod dynamic;
+#[cfg(feature = "either")]
+pub mod either_field;
+mod endianness;
+pub mod fixed_str;
+pub mod framed;
+#[cfg(feature = "std")]
+mod framed_writer;
+mod header;
+mod message;
+pub mod net;
+pub mod optional_chain;
+mod presence_mask;
+mod ranges;
+mod read_n;
+#[cfg(feature = "std")]
+mod reader;
+pub mod rest_bytes;
+pub mod rest_vec;
 mod ser;
 mod ser_output;
+pub mod string_tagged_enum;
+#[cfg(feature = "std")]
+mod systemtime;
+mod tagged_enum;
+pub mod u16_prefixed_bytes;
+pub mod u16_prefixed_str;
+pub mod u8_variant;
+#[cfg(feature = "uuid")]
+pub mod uuid_bytes;
+#[cfg(feature = "varint-len")]
+mod varint;
 
-pub use de::{from_bytes, Deserializer};
-pub use ser::{to_bytes, Serializer};
-pub use ser_output::SerOutput;
+#[cfg(feature = "tokio")]
+pub use async_read::read_frame;
+#[cfg(feature = "tokio-codec")]
+pub use codec::SshFormatCodec;
+pub use crc::{from_bytes_crc, to_bytes_crc};
+pub use de::{
+    decode_all, from_bytes, from_bytes_consumed, from_bytes_framed, from_bytes_self_len,
+    Deserializer,
+};
+pub use endianness::Endianness;
+#[cfg(feature = "std")]
+pub use framed_writer::FramedWriter;
+pub use header::{split_header, Header};
+pub use message::{from_bytes_message, to_bytes_message, MuxMessage};
+pub use presence_mask::PresenceMask;
+pub use ranges::from_bytes_with_ranges;
+pub use read_n::{read_n, ReadN};
+#[cfg(feature = "std")]
+pub use reader::{from_reader, ReaderDeserializer};
+#[cfg(feature = "std")]
+pub use ser::to_writer;
+pub use ser::{
+    serialized_size, to_bytes, to_bytes_sized, to_bytes_with_capacity, to_io_slices, to_slice,
+    validate, LenToken, SeqToken, Serializer, SizeHint,
+};
+pub use ser_output::{LenCounter, MeteredOutput, SerOutput, SerOutputBuf, SliceWriter};
 pub use ssh_format_error::{Error, Result};
+#[cfg(feature = "std")]
+pub use systemtime::{systemtime_unix_millis, systemtime_unix_secs};
+#[cfg(feature = "varint-len")]
+pub use varint::{from_bytes_varint, to_bytes_varint};
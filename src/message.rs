@@ -0,0 +1,101 @@
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{to_bytes, Deserializer, Error, Result};
+
+/// A message of the openssh mux protocol, which is always preceded by
+/// its opcode on the wire.
+pub trait MuxMessage {
+    /// Opcode written before the body of the message.
+    const OPCODE: u32;
+}
+
+/// Return a byte array with the first 4 bytes representing the size
+/// of the rest of the serialized message, followed by `T::OPCODE`
+/// and then `value`.
+pub fn to_bytes_message<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: MuxMessage + Serialize,
+{
+    to_bytes(&(T::OPCODE, value))
+}
+
+/// Return a deserialized message and trailing bytes.
+///
+/// Just like [`crate::from_bytes`], `s` must not contain the 4-byte size.
+///
+/// The opcode is read and verified before the body is deserialized,
+/// [`Error::UnexpectedOpcode`] is returned if it does not match `T::OPCODE`.
+pub fn from_bytes_message<'a, T>(s: &'a [u8]) -> Result<(T, &'a [u8])>
+where
+    T: MuxMessage + Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::from_bytes(s);
+
+    let opcode = u32::deserialize(&mut deserializer)?;
+    if opcode != T::OPCODE {
+        return Err(Error::UnexpectedOpcode {
+            expected: T::OPCODE,
+            found: opcode,
+        });
+    }
+
+    let t = T::deserialize(&mut deserializer)?;
+    Ok((t, deserializer.into_inner().0))
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct AliveCheck {
+        request_id: u32,
+    }
+
+    impl MuxMessage for AliveCheck {
+        const OPCODE: u32 = 0x10000004;
+    }
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Terminate {
+        request_id: u32,
+    }
+
+    impl MuxMessage for Terminate {
+        const OPCODE: u32 = 0x10000005;
+    }
+
+    #[test]
+    fn test_to_bytes_message() {
+        let serialized = to_bytes_message(&AliveCheck { request_id: 3 }).unwrap();
+        assert_eq!(serialized, [0, 0, 0, 8, 0x10, 0x00, 0x00, 0x04, 0, 0, 0, 3]);
+    }
+
+    #[test]
+    fn test_message_roundtrip() {
+        let msg = AliveCheck { request_id: 0x1234 };
+        let serialized = to_bytes_message(&msg).unwrap();
+
+        let (deserialized, trailing) = from_bytes_message::<AliveCheck>(&serialized[4..]).unwrap();
+        assert_eq!(deserialized, msg);
+        assert!(trailing.is_empty());
+    }
+
+    #[test]
+    fn test_unexpected_opcode() {
+        let serialized = to_bytes_message(&AliveCheck { request_id: 1 }).unwrap();
+
+        assert_matches!(
+            from_bytes_message::<Terminate>(&serialized[4..]),
+            Err(Error::UnexpectedOpcode {
+                expected: 0x10000005,
+                found: 0x10000004,
+            })
+        );
+    }
+}
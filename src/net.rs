@@ -0,0 +1,362 @@
+//! `#[serde(with)]` helpers for IP addresses.
+//!
+//! They are written against [`core::net`], so they do not depend on
+//! `std::net` (whose types are re-exports of the same ones).
+//!
+//!  - [`ip_octets`] encodes the address as length(`u32`) + octets, which
+//!    is 4 bytes for IPv4 and 16 bytes for IPv6;
+//!  - [`ip_str`] encodes the address as its textual representation;
+//!  - [`socket_addr`] encodes the socket address as its IP address in
+//!    textual representation followed by the port as `u32`, which is how
+//!    openssh's mux protocol encodes forwarding addresses.
+
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// IP address types supported by the helpers in this module.
+pub trait IpAddress: Sized {
+    fn to_ip_addr(&self) -> IpAddr;
+
+    /// Return `None` if `addr` is not of the same version as `Self`.
+    fn from_ip_addr(addr: IpAddr) -> Option<Self>;
+}
+
+impl IpAddress for IpAddr {
+    fn to_ip_addr(&self) -> IpAddr {
+        *self
+    }
+
+    fn from_ip_addr(addr: IpAddr) -> Option<Self> {
+        Some(addr)
+    }
+}
+
+impl IpAddress for Ipv4Addr {
+    fn to_ip_addr(&self) -> IpAddr {
+        IpAddr::V4(*self)
+    }
+
+    fn from_ip_addr(addr: IpAddr) -> Option<Self> {
+        match addr {
+            IpAddr::V4(addr) => Some(addr),
+            IpAddr::V6(_) => None,
+        }
+    }
+}
+
+impl IpAddress for Ipv6Addr {
+    fn to_ip_addr(&self) -> IpAddr {
+        IpAddr::V6(*self)
+    }
+
+    fn from_ip_addr(addr: IpAddr) -> Option<Self> {
+        match addr {
+            IpAddr::V4(_) => None,
+            IpAddr::V6(addr) => Some(addr),
+        }
+    }
+}
+
+/// Encode IP address as length(`u32`) + octets in network order.
+///
+/// Use it with `#[serde(with = "ssh_format::net::ip_octets")]` on
+/// `IpAddr`, `Ipv4Addr` or `Ipv6Addr`.
+pub mod ip_octets {
+    use core::{convert::TryFrom, fmt, marker::PhantomData, net::IpAddr};
+
+    use serde::{de, Deserializer, Serializer};
+
+    use super::IpAddress;
+
+    pub fn serialize<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: IpAddress,
+    {
+        match value.to_ip_addr() {
+            IpAddr::V4(addr) => serializer.serialize_bytes(&addr.octets()),
+            IpAddr::V6(addr) => serializer.serialize_bytes(&addr.octets()),
+        }
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: IpAddress,
+    {
+        struct OctetsVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: IpAddress> de::Visitor<'de> for OctetsVisitor<T> {
+            type Value = T;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("4 or 16 octets of an IP address")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<T, E>
+            where
+                E: de::Error,
+            {
+                let addr = if let Ok(octets) = <[u8; 4]>::try_from(v) {
+                    IpAddr::from(octets)
+                } else if let Ok(octets) = <[u8; 16]>::try_from(v) {
+                    IpAddr::from(octets)
+                } else {
+                    return Err(de::Error::invalid_length(v.len(), &self));
+                };
+
+                T::from_ip_addr(addr)
+                    .ok_or_else(|| de::Error::invalid_value(de::Unexpected::Bytes(v), &self))
+            }
+        }
+
+        deserializer.deserialize_bytes(OctetsVisitor(PhantomData))
+    }
+}
+
+/// Encode IP address as a string, e.g. `"127.0.0.1"` or `"::1"`.
+///
+/// Use it with `#[serde(with = "ssh_format::net::ip_str")]` on
+/// `IpAddr`, `Ipv4Addr` or `Ipv6Addr`.
+pub mod ip_str {
+    use core::{fmt, marker::PhantomData, str::FromStr};
+
+    use serde::{de, Deserializer, Serializer};
+
+    pub fn serialize<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: fmt::Display,
+    {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        struct StrVisitor<T>(PhantomData<T>);
+
+        impl<'de, T> de::Visitor<'de> for StrVisitor<T>
+        where
+            T: FromStr,
+            T::Err: fmt::Display,
+        {
+            type Value = T;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an IP address string")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<T, E>
+            where
+                E: de::Error,
+            {
+                v.parse().map_err(de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_str(StrVisitor(PhantomData))
+    }
+}
+
+/// Encode socket address as its IP address string, e.g. `"127.0.0.1"` or
+/// `"::1"` without brackets, followed by the port as `u32`.
+///
+/// Use it with `#[serde(with = "ssh_format::net::socket_addr")]` on
+/// `SocketAddr`.
+pub mod socket_addr {
+    use core::{
+        convert::TryFrom,
+        fmt,
+        net::{IpAddr, SocketAddr},
+    };
+
+    use serde::{de, ser::SerializeTuple, Deserializer, Serializer};
+
+    struct IpStr(IpAddr);
+
+    impl serde::Serialize for IpStr {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            super::ip_str::serialize(&self.0, serializer)
+        }
+    }
+
+    impl<'de> serde::Deserialize<'de> for IpStr {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            super::ip_str::deserialize(deserializer).map(IpStr)
+        }
+    }
+
+    pub fn serialize<S>(value: &SocketAddr, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(&IpStr(value.ip()))?;
+        tuple.serialize_element(&u32::from(value.port()))?;
+        tuple.end()
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SocketAddr, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct SocketAddrVisitor;
+
+        impl<'de> de::Visitor<'de> for SocketAddrVisitor {
+            type Value = SocketAddr;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an IP address string followed by a port")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<SocketAddr, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                let IpStr(ip) = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let port: u32 = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+
+                let port = u16::try_from(port).map_err(|_| {
+                    de::Error::invalid_value(de::Unexpected::Unsigned(port.into()), &"a port")
+                })?;
+
+                Ok(SocketAddr::new(ip, port))
+            }
+        }
+
+        deserializer.deserialize_tuple(2, SocketAddrVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{from_bytes, to_bytes, Deserializer, Error};
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Forward {
+        #[serde(with = "crate::net::ip_octets")]
+        listen: Ipv4Addr,
+        #[serde(with = "crate::net::ip_octets")]
+        connect: IpAddr,
+        #[serde(with = "crate::net::ip_str")]
+        host: Ipv6Addr,
+        #[serde(with = "crate::net::ip_str")]
+        peer: IpAddr,
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let forward = Forward {
+            listen: Ipv4Addr::LOCALHOST,
+            connect: IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1)),
+            host: Ipv6Addr::LOCALHOST,
+            peer: IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)),
+        };
+
+        let serialized = to_bytes(&forward).unwrap();
+        let body = &serialized[4..];
+        assert_eq!(body[..8], [0, 0, 0, 4, 127, 0, 0, 1]);
+        assert_eq!(body[8..12], [0, 0, 0, 16]);
+        assert_eq!(body[12..14], [0xfe, 0x80]);
+        assert_eq!(body[28..35], *b"\0\0\0\x03::1");
+        assert_eq!(body[35..], *b"\0\0\0\x0b192.168.0.1");
+
+        assert_eq!(from_bytes::<Forward>(body).unwrap().0, forward);
+
+        for chunk_size in 1..body.len() {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+            assert_eq!(Forward::deserialize(&mut deserializer).unwrap(), forward);
+        }
+    }
+
+    #[test]
+    fn test_std_net() {
+        // `std::net` re-exports the `core::net` types, so the helpers
+        // work with either path.
+        #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+        struct Addr(#[serde(with = "crate::net::ip_octets")] std::net::IpAddr);
+
+        let addr = Addr(std::net::Ipv4Addr::UNSPECIFIED.into());
+        let serialized = to_bytes(&addr).unwrap();
+        assert_eq!(from_bytes::<Addr>(&serialized[4..]).unwrap().0, addr);
+    }
+
+    #[test]
+    fn test_socket_addr() {
+        use core::net::SocketAddr;
+
+        #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+        struct OpenFwd {
+            kind: u32,
+            #[serde(with = "crate::net::socket_addr")]
+            listen: SocketAddr,
+            #[serde(with = "crate::net::socket_addr")]
+            connect: SocketAddr,
+        }
+
+        let fwd = OpenFwd {
+            kind: 1,
+            listen: "127.0.0.1:8080".parse().unwrap(),
+            connect: "[::1]:22".parse().unwrap(),
+        };
+
+        let serialized = to_bytes(&fwd).unwrap();
+        let body = &serialized[4..];
+        assert_eq!(
+            body[4..],
+            *b"\0\0\0\x09127.0.0.1\0\0\x1f\x90\0\0\0\x03::1\0\0\0\x16"
+        );
+
+        assert_eq!(from_bytes::<OpenFwd>(body).unwrap().0, fwd);
+
+        for chunk_size in 1..body.len() {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+            assert_eq!(OpenFwd::deserialize(&mut deserializer).unwrap(), fwd);
+        }
+
+        // Port out of range
+        let serialized = to_bytes(&("127.0.0.1", 65536_u32)).unwrap();
+        let mut deserializer = Deserializer::from_bytes(&serialized[4..]);
+        assert!(matches!(
+            crate::net::socket_addr::deserialize(&mut deserializer),
+            Err(Error::Message(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid() {
+        use crate::net::{ip_octets, ip_str};
+
+        let serialized = to_bytes(&vec![1_u8, 2, 3, 4, 5]).unwrap();
+        let mut deserializer = Deserializer::from_bytes(&serialized[4..]);
+        assert!(matches!(
+            ip_octets::deserialize::<_, IpAddr>(&mut deserializer),
+            Err(Error::Message(_))
+        ));
+
+        let serialized = to_bytes(&Ipv6Addr::LOCALHOST.octets().to_vec()).unwrap();
+        let mut deserializer = Deserializer::from_bytes(&serialized[4..]);
+        assert!(matches!(
+            ip_octets::deserialize::<_, Ipv4Addr>(&mut deserializer),
+            Err(Error::Message(_))
+        ));
+
+        let serialized = to_bytes(&"localhost").unwrap();
+        let mut deserializer = Deserializer::from_bytes(&serialized[4..]);
+        assert!(matches!(
+            ip_str::deserialize::<_, IpAddr>(&mut deserializer),
+            Err(Error::Message(_))
+        ));
+    }
+}
@@ -0,0 +1,163 @@
+//! Encode a sequence as a chain of `[true][element]`, terminated by
+//! `[false]`, where each flag is a `bool` encoded as `u32`.
+//!
+//! This is a common pattern for messages ending with an arbitrary number
+//! of optional sub-records.
+//!
+//! Use it with `#[serde(with = "ssh_format::optional_chain")]`.
+
+use alloc::vec::Vec;
+use core::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{self, SeqAccess},
+    ser::SerializeTuple,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+pub fn serialize<S, T>(value: &[T], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize,
+{
+    let mut tuple = serializer.serialize_tuple(2 * value.len() + 1)?;
+    for element in value {
+        tuple.serialize_element(&true)?;
+        tuple.serialize_element(element)?;
+    }
+    tuple.serialize_element(&false)?;
+    tuple.end()
+}
+
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    struct ChainVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>> de::Visitor<'de> for ChainVisitor<T> {
+        type Value = Vec<T>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a chain of elements terminated by false")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Vec<T>, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut values = Vec::new();
+            loop {
+                let has_next: bool = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(2 * values.len(), &self))?;
+                if !has_next {
+                    break Ok(values);
+                }
+
+                let value = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(2 * values.len() + 1, &self))?;
+                values.push(value);
+            }
+        }
+    }
+
+    deserializer.deserialize_tuple(usize::MAX, ChainVisitor(PhantomData))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{from_bytes, to_bytes, Deserializer};
+
+    #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        id: u32,
+        name: String,
+    }
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Message {
+        request_id: u32,
+        #[serde(with = "crate::optional_chain")]
+        records: Vec<Record>,
+    }
+
+    fn test_roundtrip(records: Vec<Record>) {
+        let message = Message {
+            request_id: 0x1234,
+            records,
+        };
+
+        let serialized = to_bytes(&message).unwrap();
+        let body = &serialized[4..];
+
+        // Terminating flag
+        assert_eq!(body[body.len() - 4..], [0, 0, 0, 0]);
+
+        let (deserialized, trailing) = from_bytes::<Message>(body).unwrap();
+        assert_eq!(deserialized, message);
+        assert!(trailing.is_empty());
+
+        for chunk_size in 1..body.len() {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+            assert_eq!(Message::deserialize(&mut deserializer).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        test_roundtrip(Vec::new());
+
+        let serialized = to_bytes(&Message {
+            request_id: 1,
+            records: Vec::new(),
+        })
+        .unwrap();
+        assert_eq!(serialized[4..], [0, 0, 0, 1, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_single() {
+        test_roundtrip(vec![Record {
+            id: 1,
+            name: "a".to_owned(),
+        }]);
+    }
+
+    #[test]
+    fn test_multiple() {
+        let records: Vec<_> = (0..5)
+            .map(|id| Record {
+                id,
+                name: "x".repeat(id as usize),
+            })
+            .collect();
+
+        let serialized = to_bytes(&Message {
+            request_id: 1,
+            records: records[..2].to_vec(),
+        })
+        .unwrap();
+        assert_eq!(
+            serialized[4..],
+            [
+                0, 0, 0, 1, // request_id
+                0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, // [true][0][""]
+                0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, b'x', // [true][1]["x"]
+                0, 0, 0, 0, // [false]
+            ]
+        );
+
+        test_roundtrip(records);
+    }
+
+    #[test]
+    fn test_missing_terminator() {
+        let serialized = to_bytes(&(1_u32, true, 2_u32, "b")).unwrap();
+        assert!(from_bytes::<Message>(&serialized[4..]).is_err());
+    }
+}
@@ -0,0 +1,337 @@
+use core::fmt;
+
+use serde::{
+    de::{self, SeqAccess},
+    ser::{self, SerializeTuple},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// A `u32` flags word where each bit indicates whether a subsequent
+/// optional field is present, encoded as `u32`.
+///
+/// The mask is written first, followed by the present fields only, in
+/// order. It is meant to be used in a manual `Serialize`/`Deserialize`
+/// implementation of the message:
+///
+/// ```
+/// use std::fmt;
+///
+/// use serde::{de, ser::SerializeTuple, Deserialize, Deserializer, Serialize, Serializer};
+/// use ssh_format::PresenceMask;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Message {
+///     port: Option<u16>,
+///     name: Option<String>,
+/// }
+///
+/// impl Serialize for Message {
+///     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+///         let mask = PresenceMask::new()
+///             .with(0, self.port.is_some())
+///             .with(1, self.name.is_some());
+///
+///         let mut tuple = serializer.serialize_tuple(3)?;
+///         tuple.serialize_element(&mask)?;
+///         mask.serialize_field(&mut tuple, 0, &self.port)?;
+///         mask.serialize_field(&mut tuple, 1, &self.name)?;
+///         tuple.end()
+///     }
+/// }
+///
+/// impl<'de> Deserialize<'de> for Message {
+///     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+///         struct MessageVisitor;
+///
+///         impl<'de> de::Visitor<'de> for MessageVisitor {
+///             type Value = Message;
+///
+///             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+///                 formatter.write_str("a message")
+///             }
+///
+///             fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Message, A::Error> {
+///                 let mask: PresenceMask = seq
+///                     .next_element()?
+///                     .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+///
+///                 Ok(Message {
+///                     port: mask.deserialize_field(&mut seq, 0)?,
+///                     name: mask.deserialize_field(&mut seq, 1)?,
+///                 })
+///             }
+///         }
+///
+///         deserializer.deserialize_tuple(3, MessageVisitor)
+///     }
+/// }
+///
+/// let message = Message { port: None, name: Some("eth0".to_owned()) };
+///
+/// let serialized = ssh_format::to_bytes(&message).unwrap();
+/// assert_eq!(serialized[4..8], [0, 0, 0, 0b10]);
+///
+/// let (deserialized, _) = ssh_format::from_bytes::<Message>(&serialized[4..]).unwrap();
+/// assert_eq!(deserialized, message);
+/// ```
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PresenceMask(u32);
+
+impl PresenceMask {
+    /// Create a mask with no bit set.
+    pub const fn new() -> Self {
+        Self(0)
+    }
+
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Return true if `bit` is set.
+    ///
+    /// # Panics
+    ///
+    /// If `bit` is not less than 32.
+    pub const fn is_set(self, bit: u32) -> bool {
+        self.0 & Self::flag(bit) != 0
+    }
+
+    /// Return the mask with `bit` set to `present`.
+    ///
+    /// # Panics
+    ///
+    /// If `bit` is not less than 32.
+    #[must_use]
+    pub const fn with(self, bit: u32, present: bool) -> Self {
+        if present {
+            Self(self.0 | Self::flag(bit))
+        } else {
+            Self(self.0 & !Self::flag(bit))
+        }
+    }
+
+    const fn flag(bit: u32) -> u32 {
+        assert!(bit < 32, "bit of PresenceMask must be less than 32");
+        1 << bit
+    }
+
+    /// Serialize `value` if it is present.
+    ///
+    /// Return an error if the presence of `value` does not match `bit`.
+    pub fn serialize_field<S, T>(
+        self,
+        tuple: &mut S,
+        bit: u32,
+        value: &Option<T>,
+    ) -> Result<(), S::Error>
+    where
+        S: SerializeTuple,
+        T: Serialize,
+    {
+        match (self.is_set(bit), value) {
+            (true, Some(value)) => tuple.serialize_element(value),
+            (false, None) => Ok(()),
+            (is_set, _) => Err(ser::Error::custom(format_args!(
+                "presence of field does not match bit {} of the mask, which is {}",
+                bit,
+                if is_set { "set" } else { "unset" }
+            ))),
+        }
+    }
+
+    /// Deserialize the next element of `seq` if `bit` is set, otherwise
+    /// return `None` without consuming anything.
+    pub fn deserialize_field<'de, A, T>(self, seq: &mut A, bit: u32) -> Result<Option<T>, A::Error>
+    where
+        A: SeqAccess<'de>,
+        T: Deserialize<'de>,
+    {
+        if !self.is_set(bit) {
+            return Ok(None);
+        }
+
+        struct Expected(u32);
+
+        impl de::Expected for Expected {
+            fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "field of bit {}", self.0)
+            }
+        }
+
+        seq.next_element()?
+            .map(Some)
+            .ok_or_else(|| de::Error::invalid_length(bit as usize, &Expected(bit)))
+    }
+}
+
+impl Serialize for PresenceMask {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PresenceMask {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u32::deserialize(deserializer).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use serde::{de, ser::SerializeTuple, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::PresenceMask;
+    use crate::{from_bytes, to_bytes, Deserializer as SshDeserializer, Error};
+
+    #[derive(Debug, Clone, Eq, PartialEq)]
+    struct Message {
+        id: u32,
+        port: Option<u16>,
+        name: Option<String>,
+        timeout: Option<u32>,
+    }
+
+    impl Message {
+        fn mask(&self) -> PresenceMask {
+            PresenceMask::new()
+                .with(0, self.port.is_some())
+                .with(1, self.name.is_some())
+                .with(5, self.timeout.is_some())
+        }
+    }
+
+    impl Serialize for Message {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mask = self.mask();
+
+            let mut tuple = serializer.serialize_tuple(5)?;
+            tuple.serialize_element(&self.id)?;
+            tuple.serialize_element(&mask)?;
+            mask.serialize_field(&mut tuple, 0, &self.port)?;
+            mask.serialize_field(&mut tuple, 1, &self.name)?;
+            mask.serialize_field(&mut tuple, 5, &self.timeout)?;
+            tuple.end()
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Message {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct MessageVisitor;
+
+            impl<'de> de::Visitor<'de> for MessageVisitor {
+                type Value = Message;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("a message")
+                }
+
+                fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Message, A::Error> {
+                    let id = seq
+                        .next_element()?
+                        .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                    let mask: PresenceMask = seq
+                        .next_element()?
+                        .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+
+                    Ok(Message {
+                        id,
+                        port: mask.deserialize_field(&mut seq, 0)?,
+                        name: mask.deserialize_field(&mut seq, 1)?,
+                        timeout: mask.deserialize_field(&mut seq, 5)?,
+                    })
+                }
+            }
+
+            deserializer.deserialize_tuple(5, MessageVisitor)
+        }
+    }
+
+    #[test]
+    fn test_mask() {
+        let mask = PresenceMask::new().with(0, true).with(31, true);
+        assert_eq!(mask.bits(), 0x8000_0001);
+        assert!(mask.is_set(0) && mask.is_set(31) && !mask.is_set(1));
+
+        let mask = mask.with(0, false);
+        assert_eq!(mask, PresenceMask::from_bits(0x8000_0000));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let full = Message {
+            id: 7,
+            port: Some(22),
+            name: Some("eth0".to_owned()),
+            timeout: Some(30),
+        };
+
+        for (message, bits) in [
+            (full.clone(), 0b100011),
+            (
+                Message {
+                    name: None,
+                    ..full.clone()
+                },
+                0b100001,
+            ),
+            (
+                Message {
+                    port: None,
+                    timeout: None,
+                    ..full.clone()
+                },
+                0b10,
+            ),
+            (
+                Message {
+                    id: 7,
+                    port: None,
+                    name: None,
+                    timeout: None,
+                },
+                0,
+            ),
+        ] {
+            let serialized = to_bytes(&message).unwrap();
+            let body = &serialized[4..];
+            assert_eq!(body[4..8], u32::to_be_bytes(bits));
+
+            let expected_len = 8
+                + message.port.map_or(0, |_| 2)
+                + message.name.as_ref().map_or(0, |name| 4 + name.len())
+                + message.timeout.map_or(0, |_| 4);
+            assert_eq!(body.len(), expected_len);
+
+            assert_eq!(from_bytes::<Message>(body).unwrap().0, message);
+
+            for chunk_size in 1..body.len() {
+                let mut deserializer = SshDeserializer::new(body.chunks(chunk_size).fuse());
+                assert_eq!(Message::deserialize(&mut deserializer).unwrap(), message);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mismatch() {
+        struct Mismatched;
+
+        impl Serialize for Mismatched {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mask = PresenceMask::new().with(0, true);
+
+                let mut tuple = serializer.serialize_tuple(2)?;
+                tuple.serialize_element(&mask)?;
+                mask.serialize_field(&mut tuple, 0, &None::<u8>)?;
+                tuple.end()
+            }
+        }
+
+        assert!(matches!(to_bytes(&Mismatched), Err(Error::Message(_))));
+    }
+}
@@ -0,0 +1,251 @@
+use alloc::vec::Vec;
+use core::{iter, ops::Range};
+
+use serde::de::{self, DeserializeSeed, SeqAccess, Visitor};
+use serde::Deserialize;
+
+use crate::{Deserializer, Error, Result};
+
+type SliceDeserializer<'de> = Deserializer<'de, iter::Empty<&'de [u8]>>;
+
+/// Return a deserialized value along with the byte range each of its
+/// top-level fields occupies in `s`, e.g. for relaying some of them
+/// verbatim.
+///
+/// Just like [`crate::from_bytes`], `s` must not contain the 4-byte size.
+/// Values that are not a struct or tuple are reported as a single field,
+/// and trailing bytes are not covered by any range.
+pub fn from_bytes_with_ranges<'a, T>(s: &'a [u8]) -> Result<(T, Vec<Range<usize>>)>
+where
+    T: Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::from_bytes(s);
+    let mut ranges = Vec::new();
+
+    let t = T::deserialize(TopLevel {
+        deserializer: &mut deserializer,
+        input_len: s.len(),
+        ranges: &mut ranges,
+    })?;
+
+    Ok((t, ranges))
+}
+
+/// Records the range of each field of the top-level value.
+struct TopLevel<'a, 'de> {
+    deserializer: &'a mut SliceDeserializer<'de>,
+    input_len: usize,
+    ranges: &'a mut Vec<Range<usize>>,
+}
+
+impl<'a, 'de> TopLevel<'a, 'de> {
+    fn offset(&self) -> usize {
+        self.input_len - self.deserializer.slice.len()
+    }
+
+    /// Deserialize the whole value as a single field.
+    fn whole<R>(self, f: impl FnOnce(&mut SliceDeserializer<'de>) -> Result<R>) -> Result<R> {
+        let start = self.offset();
+        let value = f(&mut *self.deserializer)?;
+        let end = self.offset();
+
+        self.ranges.push(start..end);
+        Ok(value)
+    }
+
+    fn fields<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(FieldsAccess { top: self, len })
+    }
+}
+
+macro_rules! forward_whole {
+    ( $( $name:ident ( $( $arg:ident : $type:ty ),* ) )* ) => {
+        $(
+            fn $name<V>(self, $( $arg: $type, )* visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.whole(|deserializer| {
+                    de::Deserializer::$name(deserializer, $( $arg, )* visitor)
+                })
+            }
+        )*
+    };
+}
+
+impl<'a, 'de> de::Deserializer<'de> for TopLevel<'a, 'de> {
+    type Error = Error;
+
+    forward_whole! {
+        deserialize_any()
+        deserialize_bool()
+        deserialize_i8()
+        deserialize_i16()
+        deserialize_i32()
+        deserialize_i64()
+        deserialize_i128()
+        deserialize_u8()
+        deserialize_u16()
+        deserialize_u32()
+        deserialize_u64()
+        deserialize_u128()
+        deserialize_f32()
+        deserialize_f64()
+        deserialize_char()
+        deserialize_str()
+        deserialize_string()
+        deserialize_bytes()
+        deserialize_byte_buf()
+        deserialize_option()
+        deserialize_unit()
+        deserialize_unit_struct(name: &'static str)
+        deserialize_seq()
+        deserialize_map()
+        deserialize_enum(name: &'static str, variants: &'static [&'static str])
+        deserialize_identifier()
+        deserialize_ignored_any()
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.fields(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match name {
+            // Captures the rest of the input, so it is a single field.
+            crate::rest_vec::NAME | crate::rest_bytes::NAME => self.whole(|deserializer| {
+                de::Deserializer::deserialize_tuple_struct(deserializer, name, len, visitor)
+            }),
+            _ => self.fields(len, visitor),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.fields(fields.len(), visitor)
+    }
+
+    #[cfg(feature = "is_human_readable")]
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+struct FieldsAccess<'a, 'de> {
+    top: TopLevel<'a, 'de>,
+    len: usize,
+}
+
+impl<'a, 'de> SeqAccess<'de> for FieldsAccess<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+
+        let start = self.top.offset();
+        let value = seed.deserialize(&mut *self.top.deserializer)?;
+        let end = self.top.offset();
+
+        self.top.ranges.push(start..end);
+        Ok(Some(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::from_bytes_with_ranges;
+    use crate::to_bytes;
+
+    #[test]
+    fn test_struct() {
+        #[derive(Debug, Eq, PartialEq, Deserialize)]
+        struct Message<'a> {
+            id: u32,
+            host: &'a str,
+            flags: Vec<u16>,
+            port: u8,
+        }
+
+        let serialized = to_bytes(&(7_u32, "localhost", vec![1_u16, 2], 22_u8)).unwrap();
+        let body = &serialized[4..];
+
+        let (message, ranges) = from_bytes_with_ranges::<Message>(body).unwrap();
+        assert_eq!(
+            message,
+            Message {
+                id: 7,
+                host: "localhost",
+                flags: vec![1, 2],
+                port: 22,
+            }
+        );
+        assert_eq!(ranges, [0..4, 4..17, 17..25, 25..26]);
+
+        assert_eq!(
+            body[ranges[1].clone()],
+            to_bytes(&"localhost").unwrap()[4..]
+        );
+        assert_eq!(
+            body[ranges[2].clone()],
+            to_bytes(&vec![1_u16, 2]).unwrap()[4..]
+        );
+        assert_eq!(body[ranges[3].clone()], [22]);
+    }
+
+    #[test]
+    fn test_non_compound() {
+        let serialized = to_bytes(&"Hello").unwrap();
+        let (s, ranges) = from_bytes_with_ranges::<String>(&serialized[4..]).unwrap();
+        assert_eq!(s, "Hello");
+        assert_eq!(ranges, vec![0..9]);
+
+        // Newtype structs are transparent
+        #[derive(Deserialize)]
+        struct Wrapper((u8, u16));
+
+        let serialized = to_bytes(&(1_u8, 2_u16, 3_u8)).unwrap();
+        let (wrapper, ranges) = from_bytes_with_ranges::<Wrapper>(&serialized[4..]).unwrap();
+        assert_eq!(wrapper.0, (1, 2));
+        assert_eq!(ranges, [0..1, 1..3]);
+    }
+}
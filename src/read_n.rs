@@ -0,0 +1,186 @@
+use alloc::vec::Vec;
+use core::{fmt, marker::PhantomData};
+
+use serde::de::{self, DeserializeSeed, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+
+/// Upper bound of elements preallocated, since `n` usually comes from
+/// untrusted input.
+const MAX_PREALLOC: usize = 4096;
+
+/// [`DeserializeSeed`] reading exactly `n` values of `T` back-to-back,
+/// without a count in the input.
+///
+/// It supports layouts where the count is given by an earlier field,
+/// which `#[derive(Deserialize)]` cannot express:
+///
+/// ```
+/// use std::fmt;
+///
+/// use serde::de::{self, Deserializer, SeqAccess, Visitor};
+/// use serde::Deserialize;
+/// use ssh_format::ReadN;
+///
+/// #[derive(Debug, PartialEq, Deserialize)]
+/// struct Record {
+///     id: u32,
+///     flags: u8,
+/// }
+///
+/// /// `n: u32` followed by `n` records.
+/// #[derive(Debug, PartialEq)]
+/// struct Records(Vec<Record>);
+///
+/// impl<'de> Deserialize<'de> for Records {
+///     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+///         struct RecordsVisitor;
+///
+///         impl<'de> Visitor<'de> for RecordsVisitor {
+///             type Value = Records;
+///
+///             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+///                 formatter.write_str("count followed by records")
+///             }
+///
+///             fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Records, A::Error> {
+///                 let n: u32 = seq
+///                     .next_element()?
+///                     .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+///                 let records = seq
+///                     .next_element_seed(ReadN::new(n as usize))?
+///                     .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+///                 Ok(Records(records))
+///             }
+///         }
+///
+///         // The count and the records
+///         deserializer.deserialize_tuple(2, RecordsVisitor)
+///     }
+/// }
+///
+/// let bytes = [0, 0, 0, 2, 0, 0, 0, 1, 0xa, 0, 0, 0, 2, 0xb];
+/// let (records, _) = ssh_format::from_bytes::<Records>(&bytes).unwrap();
+/// assert_eq!(
+///     records,
+///     Records(vec![Record { id: 1, flags: 0xa }, Record { id: 2, flags: 0xb }])
+/// );
+/// ```
+#[derive(Debug)]
+pub struct ReadN<T> {
+    n: usize,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> ReadN<T> {
+    pub const fn new(n: usize) -> Self {
+        Self {
+            n,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> DeserializeSeed<'de> for ReadN<T> {
+    type Value = Vec<T>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(self.n, self)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for ReadN<T> {
+    type Value = Vec<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{} values", self.n)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Vec<T>, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut values = Vec::with_capacity(self.n.min(MAX_PREALLOC));
+        for i in 0..self.n {
+            let value = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+            values.push(value);
+        }
+        Ok(values)
+    }
+}
+
+/// Deserialize exactly `n` values of `T` back-to-back, without a count
+/// in the input.
+///
+/// See [`ReadN`] for using it inside a `Deserialize` implementation.
+pub fn read_n<'de, D, T>(deserializer: D, n: usize) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    ReadN::new(n).deserialize(deserializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use serde::{Deserialize, Serialize};
+
+    use super::read_n;
+    use crate::{to_bytes, Deserializer, Error};
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        id: u32,
+        name: String,
+    }
+
+    #[test]
+    fn test_read_n() {
+        let records: Vec<_> = (0..3)
+            .map(|id| Record {
+                id,
+                name: id.to_string(),
+            })
+            .collect();
+
+        // Count field followed by the records without their own count.
+        let serialized = to_bytes(&(
+            records.len() as u32,
+            &records[0],
+            &records[1],
+            &records[2],
+            0xff_u8,
+        ))
+        .unwrap();
+        let body = &serialized[4..];
+
+        for chunk_size in 1..=body.len() {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+
+            let n = u32::deserialize(&mut deserializer).unwrap();
+            let deserialized: Vec<Record> = read_n(&mut deserializer, n as usize).unwrap();
+            assert_eq!(deserialized, records);
+
+            assert_eq!(u8::deserialize(&mut deserializer).unwrap(), 0xff);
+            assert!(!deserializer.has_remaining_data());
+        }
+
+        let mut deserializer = Deserializer::from_bytes(&body[4..]);
+        assert!(read_n::<_, Record>(&mut deserializer, 0)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_read_n_eof() {
+        let serialized = to_bytes(&(1_u32, 2_u32)).unwrap();
+
+        let mut deserializer = Deserializer::from_bytes(&serialized[4..]);
+        assert_matches!(read_n::<_, u32>(&mut deserializer, 3), Err(Error::Eof));
+    }
+}
@@ -0,0 +1,356 @@
+use std::{
+    borrow::Cow,
+    io::{self, Read},
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::{de::Input, Endianness, Error, Result};
+
+/// Deserializer pulling bytes from an [`io::Read`] on demand, e.g. a
+/// socket, instead of requiring the whole input upfront.
+///
+/// Since the bytes are not kept around, only owned strings and bytes can
+/// be deserialized, e.g. `String` and `Vec<u8>` but not `&str`.
+///
+/// The reader is read in small pieces, so it should be buffered, e.g.
+/// with [`io::BufReader`].
+///
+/// Checking for remaining data, e.g. when deserializing a trailing
+/// `Option`, reads one byte ahead, which blocks until the peer sends more
+/// data or closes the connection. The byte read ahead is lost by
+/// [`Self::into_inner`]. On a socket, bound the reader to the current
+/// message with [`Read::take`] to avoid both, as shown in [`from_reader`].
+#[derive(Debug)]
+pub struct ReaderDeserializer<R> {
+    reader: R,
+    /// Byte read ahead to check for remaining data.
+    peeked: Option<u8>,
+    pub(crate) lossy_strings: bool,
+    pub(crate) lenient_bool: bool,
+    max_len: usize,
+    endianness: Endianness,
+}
+
+impl<R> ReaderDeserializer<R> {
+    pub const fn new(reader: R) -> Self {
+        Self {
+            reader,
+            peeked: None,
+            lossy_strings: false,
+            lenient_bool: false,
+            max_len: usize::MAX,
+            endianness: Endianness::Big,
+        }
+    }
+
+    /// Same as [`crate::Deserializer::lossy_strings`].
+    pub const fn lossy_strings(mut self, lossy_strings: bool) -> Self {
+        self.lossy_strings = lossy_strings;
+        self
+    }
+
+    /// Same as [`crate::Deserializer::lenient_bool`].
+    pub const fn lenient_bool(mut self, lenient_bool: bool) -> Self {
+        self.lenient_bool = lenient_bool;
+        self
+    }
+
+    /// Same as [`crate::Deserializer::max_len`].
+    pub const fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Same as [`crate::Deserializer::endianness`].
+    pub const fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Return the underlying reader.
+    ///
+    /// Checking for remaining data, e.g. when deserializing a trailing
+    /// `Option`, reads one byte ahead, which is lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<'de, R: Read> Input<'de> for ReaderDeserializer<R> {
+    fn next_byte(&mut self) -> Result<u8> {
+        let mut byte = [0];
+        self.fill_buffer(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    fn fill_buffer(&mut self, buffer: &mut [u8]) -> Result<()> {
+        let buffer = match (self.peeked, buffer) {
+            (_, []) => return Ok(()),
+            (Some(byte), [first, rest @ ..]) => {
+                self.peeked = None;
+                *first = byte;
+                rest
+            }
+            (None, buffer) => buffer,
+        };
+
+        self.reader.read_exact(buffer)?;
+        Ok(())
+    }
+
+    /// The buffer only grows with the bytes actually read, so that a bogus
+    /// length does not allocate a huge buffer upfront.
+    fn next_bytes(&mut self, size: usize) -> Result<Cow<'de, [u8]>> {
+        let mut bytes = Vec::new();
+        if size != 0 {
+            bytes.extend(self.peeked.take());
+        }
+
+        let remaining = (size - bytes.len()) as u64;
+        self.reader
+            .by_ref()
+            .take(remaining)
+            .read_to_end(&mut bytes)?;
+
+        if bytes.len() != size {
+            return Err(Error::Eof);
+        }
+
+        Ok(Cow::Owned(bytes))
+    }
+
+    fn has_remaining_input(&mut self) -> Result<bool> {
+        if self.peeked.is_some() {
+            return Ok(true);
+        }
+
+        let mut byte = [0];
+        loop {
+            match self.reader.read(&mut byte) {
+                Ok(0) => break Ok(false),
+                Ok(_) => {
+                    self.peeked = Some(byte[0]);
+                    break Ok(true);
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => break Err(err.into()),
+            }
+        }
+    }
+
+    fn take_rest(&mut self) -> Result<Cow<'de, [u8]>> {
+        let mut bytes: Vec<u8> = self.peeked.take().into_iter().collect();
+        self.reader.read_to_end(&mut bytes)?;
+
+        Ok(Cow::Owned(bytes))
+    }
+
+    fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+}
+
+/// Deserialize a value from `reader`, reading only as many bytes as it
+/// needs, except for a trailing `Option` or [`crate::rest_vec`] field
+/// which reads until EOF.
+///
+/// Just like [`crate::from_bytes`], the 4-byte size is not expected, so
+/// to read a single message from a stream, read the size first, then
+/// limit the reader to it with [`Read::take`].
+///
+/// This is required for a live socket: reading until EOF, or even the
+/// one byte read ahead to check for a trailing `Option`, would block
+/// until the peer closes the connection, and would consume bytes of the
+/// next message.
+///
+/// ```
+/// use std::io::Read;
+///
+/// let serialized = ssh_format::to_bytes(&(1_u32, "Hello".to_owned())).unwrap();
+/// let mut stream = &serialized[..];
+///
+/// let mut header = [0_u8; 4];
+/// stream.read_exact(&mut header).unwrap();
+/// let len = u32::from_be_bytes(header);
+///
+/// let value: (u32, String) = ssh_format::from_reader(stream.take(len.into())).unwrap();
+/// assert_eq!(value, (1, "Hello".to_owned()));
+/// ```
+pub fn from_reader<R, T>(reader: R) -> Result<T>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    T::deserialize(&mut ReaderDeserializer::new(reader))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fmt::Debug, io};
+
+    use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+    use super::*;
+    use crate::to_bytes;
+
+    /// Reader returning at most one byte per read.
+    struct OneByteReader<'a>(&'a [u8]);
+
+    impl io::Read for OneByteReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(1);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    fn test_roundtrip<T>(value: &T)
+    where
+        T: Debug + Eq + Serialize + DeserializeOwned,
+    {
+        let serialized = to_bytes(value).unwrap();
+        let body = &serialized[4..];
+
+        assert_eq!(from_reader::<_, T>(body).unwrap(), *value);
+        assert_eq!(from_reader::<_, T>(OneByteReader(body)).unwrap(), *value);
+    }
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    enum Request {
+        Ping,
+        Open { id: u32, path: String },
+    }
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Message {
+        id: u32,
+        name: String,
+        data: Vec<u8>,
+        requests: Vec<Request>,
+        c: char,
+        flag: bool,
+        large: u64,
+        trailing: Option<(i16, String)>,
+    }
+
+    fn message() -> Message {
+        Message {
+            id: 0x1234,
+            name: "Hello".to_owned(),
+            data: vec![0, 1, 0xff],
+            requests: vec![
+                Request::Ping,
+                Request::Open {
+                    id: 2,
+                    path: "/tmp".to_owned(),
+                },
+            ],
+            c: '\u{1F980}',
+            flag: true,
+            large: u64::MAX,
+            trailing: Some((-1, String::new())),
+        }
+    }
+
+    #[test]
+    fn test_reader() {
+        test_roundtrip(&message());
+        test_roundtrip(&Message {
+            trailing: None,
+            ..message()
+        });
+        test_roundtrip(&(1_u8, vec!["a".to_owned(), String::new()]));
+    }
+
+    #[test]
+    fn test_rest_vec() {
+        #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+        struct Rest {
+            id: u32,
+            #[serde(with = "crate::rest_vec")]
+            rest: Vec<u16>,
+        }
+
+        test_roundtrip(&Rest {
+            id: 1,
+            rest: vec![1, 2, 3],
+        });
+        test_roundtrip(&Rest {
+            id: 1,
+            rest: Vec::new(),
+        });
+    }
+
+    #[test]
+    fn test_leaves_rest_unread() {
+        let serialized = to_bytes(&(1_u32, "Hello", 2_u8)).unwrap();
+        let mut body = &serialized[4..];
+
+        let mut deserializer = ReaderDeserializer::new(&mut body);
+        assert_eq!(
+            <(u32, String)>::deserialize(&mut deserializer).unwrap().1,
+            "Hello"
+        );
+        assert_eq!(body, [2]);
+    }
+
+    #[test]
+    fn test_eof() {
+        let serialized = to_bytes(&Message {
+            trailing: None,
+            ..message()
+        })
+        .unwrap();
+        let body = &serialized[4..];
+
+        for len in 0..body.len() {
+            assert!(matches!(
+                from_reader::<_, Message>(&body[..len]),
+                Err(Error::Eof)
+            ));
+        }
+
+        // Length prefix larger than the input
+        let body = [0xff, 0xff, 0xff, 0xff, b'a'];
+        assert!(matches!(
+            from_reader::<_, String>(&body[..]),
+            Err(Error::Eof)
+        ));
+    }
+
+    #[test]
+    fn test_borrowed_unsupported() {
+        let serialized = to_bytes(&"Hello").unwrap();
+        let mut deserializer = ReaderDeserializer::new(&serialized[4..]);
+        assert!(matches!(
+            <&str>::deserialize(&mut deserializer),
+            Err(Error::Message(_))
+        ));
+    }
+
+    #[test]
+    fn test_io_error() {
+        struct Failing;
+
+        impl io::Read for Failing {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::ConnectionReset.into())
+            }
+        }
+
+        assert!(matches!(
+            from_reader::<_, u32>(Failing),
+            Err(Error::IoError(err)) if err.kind() == io::ErrorKind::ConnectionReset
+        ));
+    }
+}
@@ -0,0 +1,136 @@
+//! Encode bytes as-is without length.
+//!
+//! On deserialization, all remaining bytes of the input are captured,
+//! thus it can only be used on the last field of a message.
+//! They are borrowed from the input if it is contiguous.
+//!
+//! Use it with `#[serde(with = "ssh_format::rest_bytes")]` on any type
+//! implementing `AsRef<[u8]>` and `From<Cow<'de, [u8]>>`, e.g. `Vec<u8>`
+//! or `Cow<'de, [u8]>`.
+
+use alloc::{borrow::Cow, vec::Vec};
+use core::fmt;
+
+use serde::{de, ser::SerializeTuple, Deserializer, Serializer};
+
+/// Name of the tuple struct recognized by [`crate::Deserializer`] to
+/// capture all remaining bytes.
+pub(crate) const NAME: &str = "$ssh_format::rest_bytes";
+
+pub fn serialize<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: AsRef<[u8]>,
+{
+    let bytes = value.as_ref();
+
+    let mut tuple = serializer.serialize_tuple(bytes.len())?;
+    for byte in bytes {
+        tuple.serialize_element(byte)?;
+    }
+    tuple.end()
+}
+
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: From<Cow<'de, [u8]>>,
+{
+    struct RestVisitor;
+
+    impl<'de> de::Visitor<'de> for RestVisitor {
+        type Value = Cow<'de, [u8]>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("remaining bytes")
+        }
+
+        fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+            Ok(Cow::Borrowed(v))
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+            Ok(Cow::Owned(v.to_vec()))
+        }
+
+        fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+            Ok(Cow::Owned(v))
+        }
+    }
+
+    deserializer
+        .deserialize_tuple_struct(NAME, usize::MAX, RestVisitor)
+        .map(T::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{from_bytes, to_bytes, Deserializer};
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Message<'a> {
+        header: u32,
+        #[serde(borrow, with = "crate::rest_bytes")]
+        rest: Cow<'a, [u8]>,
+    }
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct OwnedMessage {
+        header: u32,
+        #[serde(with = "crate::rest_bytes")]
+        rest: Vec<u8>,
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let message = Message {
+            header: 0x12345678,
+            rest: Cow::Borrowed(b"\x00\x01arbitrary trailing data"),
+        };
+
+        let serialized = to_bytes(&message).unwrap();
+        let body = &serialized[4..];
+        assert_eq!(body[..4], [0x12, 0x34, 0x56, 0x78]);
+        assert_eq!(body[4..], *message.rest);
+
+        // Borrowed from contiguous input
+        let (deserialized, trailing) = from_bytes::<Message>(body).unwrap();
+        assert_eq!(deserialized, message);
+        assert!(matches!(deserialized.rest, Cow::Borrowed(_)));
+        assert!(trailing.is_empty());
+
+        // Collected from chunks
+        for chunk_size in 1..body.len() {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+            assert_eq!(Message::deserialize(&mut deserializer).unwrap(), message);
+            assert!(!deserializer.has_remaining_data());
+
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+            assert_eq!(
+                OwnedMessage::deserialize(&mut deserializer).unwrap(),
+                OwnedMessage {
+                    header: message.header,
+                    rest: message.rest.to_vec(),
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        let message = OwnedMessage {
+            header: 1,
+            rest: Vec::new(),
+        };
+
+        let serialized = to_bytes(&message).unwrap();
+        assert_eq!(
+            from_bytes::<OwnedMessage>(&serialized[4..]).unwrap().0,
+            message
+        );
+    }
+}
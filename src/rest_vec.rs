@@ -0,0 +1,232 @@
+//! Encode a sequence as its elements back-to-back without length.
+//!
+//! On deserialization, elements are read until the input is exhausted,
+//! thus it can only be used on the last field of a message.
+//!
+//! Use it with `#[serde(with = "ssh_format::rest_vec")]`.
+//!
+//! For elements of a fixed encoded size, [`deserialize_rest_array`] also
+//! checks that the remaining bytes hold a whole number of elements.
+
+use alloc::{borrow::Cow, vec::Vec};
+use core::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{self, DeserializeOwned, SeqAccess},
+    ser::SerializeTuple,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::from_bytes;
+
+/// Name of the tuple struct recognized by [`crate::Deserializer`] to
+/// deserialize until the input is exhausted.
+pub(crate) const NAME: &str = "$ssh_format::rest_vec";
+
+pub fn serialize<S, T>(value: &[T], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize,
+{
+    let mut tuple = serializer.serialize_tuple(value.len())?;
+    for element in value {
+        tuple.serialize_element(element)?;
+    }
+    tuple.end()
+}
+
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    struct VecVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>> de::Visitor<'de> for VecVisitor<T> {
+        type Value = Vec<T>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a sequence")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Vec<T>, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut values = Vec::new();
+            while let Some(value) = seq.next_element()? {
+                values.push(value);
+            }
+            Ok(values)
+        }
+    }
+
+    deserializer.deserialize_tuple_struct(NAME, usize::MAX, VecVisitor(PhantomData))
+}
+
+/// Deserialize all remaining bytes as elements each encoded in exactly
+/// `elem_size` bytes, with their count implied by the remaining length.
+///
+/// Return an error if the remaining length is not a multiple of
+/// `elem_size`, or if an element is not encoded in exactly `elem_size`
+/// bytes.
+///
+/// Since it takes an extra argument, use it through a wrapper with
+/// `#[serde(deserialize_with = "...")]`:
+///
+/// ```
+/// use serde::{Deserialize, Deserializer};
+///
+/// fn deserialize_u32s<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u32>, D::Error> {
+///     ssh_format::rest_vec::deserialize_rest_array(deserializer, 4)
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Message {
+///     request_id: u32,
+///     #[serde(deserialize_with = "deserialize_u32s")]
+///     rest: Vec<u32>,
+/// }
+/// ```
+pub fn deserialize_rest_array<'de, D, T>(
+    deserializer: D,
+    elem_size: usize,
+) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    if elem_size == 0 {
+        return Err(de::Error::custom("element size must not be 0"));
+    }
+
+    let bytes: Cow<'de, [u8]> = crate::rest_bytes::deserialize(deserializer)?;
+
+    let chunks = bytes.chunks_exact(elem_size);
+    if !chunks.remainder().is_empty() {
+        return Err(de::Error::custom(format_args!(
+            "{} remaining bytes are not a multiple of the element size {}",
+            bytes.len(),
+            elem_size
+        )));
+    }
+
+    chunks
+        .map(|chunk| match from_bytes(chunk) {
+            Ok((value, [])) => Ok(value),
+            Ok((_, trailing)) => Err(de::Error::custom(format_args!(
+                "element is encoded in {} bytes instead of {}",
+                elem_size - trailing.len(),
+                elem_size
+            ))),
+            Err(err) => Err(de::Error::custom(err)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::deserialize_rest_array;
+    use crate::{from_bytes, to_bytes, Deserializer, Error};
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Message {
+        request_id: u32,
+        flags: u16,
+        #[serde(with = "crate::rest_vec")]
+        rest: Vec<u32>,
+    }
+
+    fn test_roundtrip(message: &Message) {
+        let serialized = to_bytes(message).unwrap();
+        let body = &serialized[4..];
+
+        assert_eq!(body.len(), 6 + 4 * message.rest.len());
+
+        let (deserialized, trailing) = from_bytes::<Message>(body).unwrap();
+        assert_eq!(&deserialized, message);
+        assert!(trailing.is_empty());
+
+        for chunk_size in 1..body.len() {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+            assert_eq!(&Message::deserialize(&mut deserializer).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        test_roundtrip(&Message {
+            request_id: 1,
+            flags: 0x0102,
+            rest: Vec::new(),
+        });
+    }
+
+    #[test]
+    fn test_non_empty() {
+        test_roundtrip(&Message {
+            request_id: 1,
+            flags: 0x0102,
+            rest: vec![0x12345678, 0, u32::MAX],
+        });
+    }
+
+    #[derive(Debug, Eq, PartialEq, Deserialize)]
+    struct ArrayMessage {
+        request_id: u32,
+        #[serde(deserialize_with = "deserialize_u32s")]
+        rest: Vec<u32>,
+    }
+
+    fn deserialize_u32s<'de, D>(deserializer: D) -> Result<Vec<u32>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserialize_rest_array(deserializer, 4)
+    }
+
+    #[test]
+    fn test_rest_array() {
+        let message = Message {
+            request_id: 1,
+            flags: 0x0102,
+            rest: vec![0x12345678, 0, u32::MAX],
+        };
+        let serialized = to_bytes(&message).unwrap();
+        // Skip the flags
+        let body = [&serialized[4..8], &serialized[10..]].concat();
+
+        let expected = ArrayMessage {
+            request_id: 1,
+            rest: message.rest,
+        };
+        assert_eq!(from_bytes::<ArrayMessage>(&body).unwrap().0, expected);
+
+        for chunk_size in 1..body.len() {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+            assert_eq!(
+                ArrayMessage::deserialize(&mut deserializer).unwrap(),
+                expected
+            );
+        }
+
+        assert_eq!(
+            from_bytes::<ArrayMessage>(&body[..4]).unwrap().0,
+            ArrayMessage {
+                request_id: 1,
+                rest: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_rest_array_not_multiple() {
+        let serialized = to_bytes(&(1_u32, [0_u8; 6])).unwrap();
+        assert!(matches!(
+            from_bytes::<ArrayMessage>(&serialized[4..]),
+            Err(Error::Message(_))
+        ));
+    }
+}
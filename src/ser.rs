@@ -1,7 +1,19 @@
+use alloc::vec::Vec;
+use core::{
+    convert::TryInto,
+    mem,
+    net::{IpAddr, SocketAddr},
+};
+#[cfg(feature = "std")]
+use std::io;
+
 use serde::{ser, Serialize};
-use std::convert::TryInto;
 
-use crate::{Error, Result, SerOutput};
+#[cfg(feature = "std")]
+use crate::ser_output::WriteOutput;
+use crate::{
+    split_header, Endianness, Error, LenCounter, Result, SerOutput, SerOutputBuf, SliceWriter,
+};
 
 fn usize_to_u32(v: usize) -> Result<u32> {
     v.try_into().map_err(|_| Error::TooLong)
@@ -11,6 +23,19 @@ fn usize_to_u32(v: usize) -> Result<u32> {
 pub struct Serializer<T: SerOutput = Vec<u8>> {
     pub output: T,
     len: usize,
+    max_output: usize,
+    strict: bool,
+    field_count_trailer: bool,
+    include_header_in_len: bool,
+    canonical: bool,
+    endianness: Endianness,
+    /// Nesting level of the value being serialized, 0 for top-level.
+    depth: usize,
+    field_count: u32,
+    /// Whether the field count trailer is already appended.
+    trailer_written: bool,
+    #[cfg(feature = "map")]
+    sort_maps: bool,
 }
 
 impl<T: SerOutput + Default> Default for Serializer<T> {
@@ -21,35 +46,294 @@ impl<T: SerOutput + Default> Default for Serializer<T> {
 
 impl<T: SerOutput> Serializer<T> {
     pub fn new(output: T) -> Self {
-        Self { output, len: 0 }
+        Self {
+            output,
+            len: 0,
+            max_output: usize::MAX,
+            strict: false,
+            field_count_trailer: false,
+            include_header_in_len: false,
+            canonical: false,
+            endianness: Endianness::Big,
+            depth: 0,
+            field_count: 0,
+            trailer_written: false,
+            #[cfg(feature = "map")]
+            sort_maps: false,
+        }
+    }
+
+    /// Create a serializer that fails with [`Error::TooLong`] as soon as
+    /// more than `max` bytes would be written, instead of only discovering
+    /// an oversized message in [`Self::create_header`].
+    ///
+    /// The limit applies to the internal counter, i.e. bytes written since
+    /// creation or the last [`Self::reset_counter`], and also caps how much
+    /// is reserved upfront.
+    pub fn with_max_output(output: T, max: usize) -> Self {
+        Self {
+            max_output: max,
+            ..Self::new(output)
+        }
+    }
+
+    /// Enable or disable strict mode, which is disabled by default.
+    ///
+    /// In strict mode, values that would otherwise be silently omitted
+    /// are rejected with `Error::Unsupported(&"implicit omission")`,
+    /// including:
+    ///  - `Option::None`;
+    ///  - `()`;
+    ///  - unit struct, e.g. `struct Unit;` or `PhantomData`.
+    ///
+    /// This is intended as a development aid for catching accidental
+    /// `Option`/unit fields.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Enable or disable canonical mode, which is disabled by default.
+    ///
+    /// In canonical mode, the output only depends on the serialized value,
+    /// so it is byte-stable and suitable for signing, e.g. computing an HMAC.
+    /// Inputs which would otherwise make the encoding ambiguous are rejected:
+    ///  - strings containing NUL bytes, which are stripped by default, fail
+    ///    with `Error::Unsupported(&"NUL byte in canonical mode")`;
+    ///  - maps fail with `Error::Unsupported(&"unsorted map in canonical mode")`
+    ///    unless sorted by `Serializer::sort_maps` (feature `map`), since the
+    ///    iteration order of e.g. `HashMap` is unspecified.
+    pub fn canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    /// Enable or disable the field count trailer, which is disabled by default.
+    ///
+    /// When enabled, the number of values serialized at the top level is
+    /// tracked and [`Self::finish_header`] appends it as a trailing `u32`,
+    /// which a decoder can check to validate the structure. Each top-level
+    /// value counts as one field, whatever its type, except omitted ones
+    /// like `Option::None`.
+    ///
+    /// The count is unspecified once serialization fails, until
+    /// [`Self::reset_counter`] is called.
+    ///
+    /// Use [`crate::Deserializer::check_field_count_trailer`] to check it
+    /// on the decode side.
+    pub fn enable_field_count_trailer(mut self, field_count_trailer: bool) -> Self {
+        self.field_count_trailer = field_count_trailer;
+        self
+    }
+
+    /// Enable or disable sorting of map entries, which is disabled by default.
+    ///
+    /// When enabled, entries are sorted by the bytes of their serialized
+    /// keys, so maps with unspecified iteration order, e.g. `HashMap`,
+    /// always serialize identically. Otherwise, entries are written in
+    /// iteration order, which is deterministic only for ordered maps like
+    /// `BTreeMap`.
+    ///
+    /// Entries are buffered before being written when enabled.
+    #[cfg(feature = "map")]
+    pub fn sort_maps(mut self, sort_maps: bool) -> Self {
+        self.sort_maps = sort_maps;
+        self
+    }
+
+    /// Set whether the length returned by [`Self::create_header`] includes
+    /// the 4-byte header itself, which is disabled by default as required
+    /// by the openssh mux protocol.
+    ///
+    /// Some other SSH framings count the length field as part of the
+    /// packet, enable this for them.
+    pub fn include_header_in_len(mut self, include_header_in_len: bool) -> Self {
+        self.include_header_in_len = include_header_in_len;
+        self
+    }
+
+    /// Set the byte order of integers, including lengths and the header
+    /// returned by [`Self::create_header`], which is big endian by default
+    /// as required by the openssh mux protocol.
+    pub fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    fn u32_to_bytes(&self, v: u32) -> [u8; 4] {
+        match self.endianness {
+            Endianness::Big => v.to_be_bytes(),
+            Endianness::Little => v.to_le_bytes(),
+        }
     }
 
     pub fn reserve(&mut self, additional: usize) {
-        self.output.reserve(additional);
+        let remaining = self.max_output.saturating_sub(self.len);
+        self.output.reserve(additional.min(remaining));
     }
 
+    /// Return the length to write before the serialized data, which counts
+    /// the bytes serialized since creation or the last
+    /// [`Self::reset_counter`] plus `len`, and the 4 bytes of the header
+    /// itself if [`Self::include_header_in_len`] is enabled.
+    ///
     /// * `len` - length of additional data included in the packet.
     pub fn create_header(&self, len: u32) -> Result<[u8; 4]> {
-        let len: u32 = usize_to_u32(self.len + len as usize)?;
+        let header_len = if self.include_header_in_len { 4 } else { 0 };
+        let len: u32 = usize_to_u32(self.len + len as usize + header_len)?;
+
+        Ok(self.u32_to_bytes(len))
+    }
+
+    /// Same as [`Self::create_header`], but write the header into the first
+    /// 4 bytes of `dst`, e.g. a buffer preallocated for vectored IO.
+    ///
+    /// Return [`Error::BufferTooSmall`] if `dst` is shorter than 4 bytes.
+    pub fn write_header_into(&self, dst: &mut [u8], extra_len: u32) -> Result<()> {
+        let dst = dst
+            .get_mut(..4)
+            .ok_or(Error::BufferTooSmall { needed: 4 })?;
+        dst.copy_from_slice(&self.create_header(extra_len)?);
+        Ok(())
+    }
 
-        Ok(len.to_be_bytes())
+    /// Same as [`Self::create_header`], but append the field count trailer
+    /// first if it is enabled by [`Self::enable_field_count_trailer`].
+    ///
+    /// The trailer is only appended once until [`Self::reset_counter`] is
+    /// called, so calling this again returns the same header.
+    pub fn finish_header(&mut self, len: u32) -> Result<[u8; 4]> {
+        if self.field_count_trailer && !self.trailer_written {
+            self.extend_from_slice(&self.u32_to_bytes(self.field_count))?;
+            self.trailer_written = true;
+        }
+        self.create_header(len)
     }
 
     /// Reset the internal counter.
-    /// This would cause [`Self::create_header`] to return `Ok([0, 0, 0, 0])`
-    /// until you call [`Serialize::serialize`] again.
+    /// This would cause [`Self::create_header`] to report an empty body,
+    /// i.e. `Ok([0, 0, 0, 0])` by default, until you call
+    /// [`Serialize::serialize`] again.
     pub fn reset_counter(&mut self) {
         self.len = 0;
+        self.depth = 0;
+        self.field_count = 0;
+        self.trailer_written = false;
+    }
+
+    /// Move the output out, leaving a default one in its place, e.g. to
+    /// hand the completed buffer to a socket while reusing the serializer.
+    ///
+    /// This also calls [`Self::reset_counter`], since the bytes counted
+    /// so far are no longer in the output.
+    pub fn take_output(&mut self) -> T
+    where
+        T: Default,
+    {
+        self.reset_counter();
+        mem::take(&mut self.output)
+    }
+
+    /// Write pre-encoded `bytes` as-is, e.g. a field serialized elsewhere.
+    ///
+    /// They are counted by [`Self::create_header`] like serialized values.
+    pub fn write_raw(&mut self, bytes: &[u8]) -> Result<()> {
+        self.reserve(bytes.len());
+
+        self.begin_field();
+        let result = self.extend_from_slice(bytes);
+        self.end_field();
+
+        result
+    }
+
+    /// Write `bytes` as length(`u32`) + content, e.g. an already-encoded
+    /// certificate embedded as a string.
+    ///
+    /// This is the same encoding as `serialize_bytes` and openssh's
+    /// `sshbuf_put_string`.
+    #[doc(alias = "write_string_field")]
+    #[doc(alias = "sshbuf_put_string")]
+    pub fn write_framed(&mut self, bytes: &[u8]) -> Result<()> {
+        ser::Serializer::serialize_bytes(self, bytes)
+    }
+
+    /// Write the raw `u32` scalar value `v` as a `char`, failing with
+    /// [`Error::InvalidChar`] if it would not deserialize as one, e.g. a
+    /// surrogate or a value larger than `char::MAX`.
+    pub fn write_char_checked(&mut self, v: u32) -> Result<()> {
+        let ch = char::from_u32(v).ok_or(Error::InvalidChar(v))?;
+        ser::Serializer::serialize_char(self, ch)
+    }
+
+    /// Write a message produced by [`to_bytes`], including its 4-byte
+    /// header, as the content of a single length-prefixed field.
+    ///
+    /// Decoding the field as bytes yields `framed` back, which can then be
+    /// decoded by e.g. [`crate::from_bytes_framed`].
+    ///
+    /// Return [`Error::InvalidLength`] if the header of `framed` does not
+    /// match the length of the rest of it, or [`Error::Eof`] if it is
+    /// shorter than a header.
+    pub fn write_nested_message(&mut self, framed: &[u8]) -> Result<()> {
+        let (header, body) = split_header(framed)?;
+        let expected: usize = header.len().try_into().map_err(|_| Error::TooLong)?;
+
+        if expected != body.len() {
+            return Err(Error::InvalidLength {
+                expected,
+                found: body.len(),
+            });
+        }
+
+        self.write_framed(framed)
+    }
+
+    /// Write `addr` as a string, e.g. `"127.0.0.1"` or `"::1"`, same as
+    /// [`crate::net::ip_str`].
+    pub fn write_ip(&mut self, addr: IpAddr) -> Result<()> {
+        crate::net::ip_str::serialize(&addr, self)
+    }
+
+    /// Write `addr` as its IP address string followed by its port as
+    /// `u32`, same as [`crate::net::socket_addr`], which is how openssh's
+    /// mux protocol encodes forwarding addresses.
+    pub fn write_socket_addr(&mut self, addr: SocketAddr) -> Result<()> {
+        crate::net::socket_addr::serialize(&addr, self)
+    }
+
+    /// Enter a value, counted as a field if it is at the top level.
+    fn begin_field(&mut self) {
+        if self.depth == 0 {
+            self.field_count += 1;
+        }
+        self.depth += 1;
+    }
+
+    fn end_field(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn check_max_output(&self, additional: usize) -> Result<()> {
+        if additional > self.max_output.saturating_sub(self.len) {
+            Err(Error::TooLong)
+        } else {
+            Ok(())
+        }
     }
 
-    fn extend_from_slice(&mut self, other: &[u8]) {
+    fn extend_from_slice(&mut self, other: &[u8]) -> Result<()> {
+        self.check_max_output(other.len())?;
         self.output.extend_from_slice(other);
         self.len += other.len();
+        Ok(())
     }
 
-    fn push(&mut self, byte: u8) {
+    fn push(&mut self, byte: u8) -> Result<()> {
+        self.check_max_output(1)?;
         self.output.push(byte);
         self.len += 1;
+        Ok(())
     }
 
     fn serialize_usize(&mut self, v: usize) -> Result<()> {
@@ -57,6 +341,158 @@ impl<T: SerOutput> Serializer<T> {
     }
 }
 
+/// Placeholder for a length, returned by [`Serializer::begin_len`].
+#[derive(Debug)]
+#[must_use = "the placeholder must be finalized with `Serializer::end_len`"]
+pub struct LenToken {
+    offset: usize,
+}
+
+/// Placeholder for an element count, returned by [`Serializer::begin_seq`].
+#[derive(Debug)]
+#[must_use = "the placeholder must be finalized with `Serializer::end_seq`"]
+pub struct SeqToken {
+    offset: usize,
+}
+
+impl Serializer<Vec<u8>> {
+    /// Create a serializer writing into a new `Vec` with at least
+    /// `capacity` bytes preallocated.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::new(Vec::with_capacity(capacity))
+    }
+}
+
+impl<T: SerOutputBuf> Serializer<T> {
+    /// Write a placeholder for a `u32` length and return a token for it.
+    ///
+    /// Call [`Self::end_len`] with the token once the data it covers is
+    /// serialized. Placeholders can be nested and finalized in any order.
+    pub fn begin_len(&mut self) -> Result<LenToken> {
+        let offset = self.output.as_slice().len();
+        self.extend_from_slice(&[0, 0, 0, 0])?;
+        Ok(LenToken { offset })
+    }
+
+    /// Backpatch the placeholder of `token` with the number of bytes
+    /// written after it.
+    pub fn end_len(&mut self, token: LenToken) -> Result<()> {
+        let start = token.offset + 4;
+        let len = usize_to_u32(self.output.as_slice().len() - start)?;
+
+        self.patch_u32(token.offset, len);
+
+        Ok(())
+    }
+
+    /// Write a placeholder for the `u32` element count of a sequence and
+    /// return a token for it.
+    ///
+    /// Elements can then be serialized one by one, e.g. from an iterator
+    /// whose length is unknown ahead, before calling [`Self::end_seq`].
+    pub fn begin_seq(&mut self) -> Result<SeqToken> {
+        let offset = self.output.as_slice().len();
+        self.extend_from_slice(&[0, 0, 0, 0])?;
+        Ok(SeqToken { offset })
+    }
+
+    /// Backpatch the placeholder of `token` with `count`.
+    pub fn end_seq(&mut self, token: SeqToken, count: u32) {
+        self.patch_u32(token.offset, count);
+    }
+
+    fn patch_u32(&mut self, offset: usize, v: u32) {
+        let bytes = self.u32_to_bytes(v);
+        self.output.as_mut_slice()[offset..offset + 4].copy_from_slice(&bytes);
+    }
+
+    /// Serialize `value` and return the bytes written for it, without any
+    /// header, for when the framing is handled elsewhere.
+    ///
+    /// ```
+    /// use ssh_format::Serializer;
+    ///
+    /// let mut serializer: Serializer = Serializer::default();
+    /// let body = serializer.serialize_value_body(&(1_u8, "a")).unwrap();
+    /// assert_eq!(body, [1, 0, 0, 0, 1, b'a']);
+    /// ```
+    pub fn serialize_value_body<V>(&mut self, value: &V) -> Result<&[u8]>
+    where
+        V: ?Sized + Serialize,
+    {
+        let start = self.output.as_slice().len();
+        value.serialize(&mut *self)?;
+        Ok(&self.output.as_slice()[start..])
+    }
+
+    /// Append `value` as a complete frame, i.e. its 4-byte size followed
+    /// by the value, same as [`to_bytes`], and return the bytes of the
+    /// frame, e.g. to pack multiple frames before a single `write_all`.
+    ///
+    /// The size follows the same rule as [`Self::create_header`], i.e. it
+    /// includes the header itself if [`Self::include_header_in_len`] is
+    /// enabled.
+    ///
+    /// Nothing is left in the output if serialization fails.
+    ///
+    /// ```
+    /// use ssh_format::{to_bytes, Serializer};
+    ///
+    /// let mut serializer: Serializer = Serializer::default();
+    /// serializer.serialize_framed(&1_u8).unwrap();
+    /// serializer.serialize_framed("a").unwrap();
+    ///
+    /// let expected = [to_bytes(&1_u8).unwrap(), to_bytes(&"a").unwrap()].concat();
+    /// assert_eq!(serializer.output, expected);
+    /// ```
+    pub fn serialize_framed<V>(&mut self, value: &V) -> Result<&[u8]>
+    where
+        V: ?Sized + Serialize,
+    {
+        let start = self.output.as_slice().len();
+        let depth = self.depth;
+        let field_count = self.field_count;
+        let header_len = if self.include_header_in_len { 4 } else { 0 };
+
+        let result = self.begin_len().and_then(|token| {
+            value.serialize(&mut *self)?;
+
+            let len = self.output.as_slice().len() - (token.offset + 4) + header_len;
+            self.patch_u32(token.offset, usize_to_u32(len)?);
+            Ok(())
+        });
+
+        if let Err(err) = result {
+            self.undo_last(self.output.as_slice().len() - start);
+            self.depth = depth;
+            self.field_count = field_count;
+            return Err(err);
+        }
+
+        Ok(&self.output.as_slice()[start..])
+    }
+
+    /// Remove the last `bytes` bytes written, e.g. to back out the field
+    /// just serialized, and decrement the internal counter accordingly.
+    ///
+    /// # Panics
+    ///
+    /// If `bytes` is larger than the internal counter, i.e. the number of
+    /// bytes written since creation or the last [`Self::reset_counter`].
+    pub fn undo_last(&mut self, bytes: usize) {
+        assert!(
+            bytes <= self.len,
+            "cannot undo {} bytes, only {} bytes are written",
+            bytes,
+            self.len
+        );
+
+        let len = self.output.as_slice().len();
+        self.output.truncate(len - bytes);
+        self.len -= bytes;
+    }
+}
+
 /// Return a byte array with the first 4 bytes representing the size
 /// of the rest of the serialized message.
 ///
@@ -65,7 +501,73 @@ pub fn to_bytes<T>(value: &T) -> Result<Vec<u8>>
 where
     T: Serialize,
 {
-    let mut buffer = vec![0, 0, 0, 0];
+    serialize_with_header(Vec::new(), value)
+}
+
+/// Size of the serialized value, advertised by the type itself.
+///
+/// Unlike a full pass over the value, this is meant to be cheap, e.g.
+/// a constant for fixed-layout structs. The default implementation
+/// returns `0`, i.e. no hint.
+pub trait SizeHint {
+    /// Expected number of bytes of the serialized value, excluding
+    /// the 4-byte length header.
+    fn size_hint(&self) -> usize {
+        0
+    }
+}
+
+/// Same as [`to_bytes`], but reserve `4 + value.size_hint()` bytes upfront,
+/// so that no reallocation happens if the hint is accurate.
+pub fn to_bytes_sized<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize + SizeHint,
+{
+    serialize_with_header(Vec::with_capacity(4 + value.size_hint()), value)
+}
+
+/// Same as [`to_bytes`], but reserve `capacity` bytes upfront, including
+/// the 4-byte header, e.g. for messages with a predictable size.
+pub fn to_bytes_with_capacity<T>(value: &T, capacity: usize) -> Result<Vec<u8>>
+where
+    T: Serialize,
+{
+    serialize_with_header(Vec::with_capacity(capacity), value)
+}
+
+/// Serialize `value` into a body and return it along with its 4-byte
+/// header, so that they can be sent with a single vectored write without
+/// copying the body.
+///
+/// ```
+/// use std::io::{IoSlice, Write};
+///
+/// let (body, header) = ssh_format::to_io_slices(&(1_u32, "Hello")).unwrap();
+///
+/// let mut stream = Vec::new();
+/// let bufs = [IoSlice::new(&header), IoSlice::new(&body)];
+/// // A real stream may write only part of the buffers.
+/// let n = stream.write_vectored(&bufs).unwrap();
+///
+/// assert_eq!(n, header.len() + body.len());
+/// assert_eq!(stream, ssh_format::to_bytes(&(1_u32, "Hello")).unwrap());
+/// ```
+pub fn to_io_slices<T>(value: &T) -> Result<(Vec<u8>, [u8; 4])>
+where
+    T: Serialize,
+{
+    let mut serializer = Serializer::default();
+    value.serialize(&mut serializer)?;
+    let header = serializer.create_header(0)?;
+
+    Ok((serializer.output, header))
+}
+
+fn serialize_with_header<T>(mut buffer: Vec<u8>, value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize,
+{
+    buffer.extend_from_slice(&[0, 0, 0, 0]);
 
     let mut serializer = Serializer::new(&mut buffer);
     value.serialize(&mut serializer)?;
@@ -76,16 +578,107 @@ where
     Ok(buffer)
 }
 
+/// Serialize into `buffer` with the first 4 bytes representing the size
+/// of the rest of the serialized message, and return the number of bytes
+/// written.
+///
+/// Return [`Error::BufferTooSmall`] with the number of bytes needed if
+/// `buffer` is too small.
+pub fn to_slice<T>(buffer: &mut [u8], value: &T) -> Result<usize>
+where
+    T: Serialize,
+{
+    let mut serializer = Serializer::new(SliceWriter::new(buffer));
+    serializer.output.extend_from_slice(&[0, 0, 0, 0]);
+
+    value.serialize(&mut serializer)?;
+    let header = serializer.create_header(0)?;
+
+    let len = serializer.output.finish()?;
+    buffer[..4].copy_from_slice(&header);
+
+    Ok(len)
+}
+
+/// Serialize `value` directly into `writer` with the first 4 bytes
+/// representing the size of the rest of the serialized message, same as
+/// [`to_bytes`] but without buffering the message.
+///
+/// `value` is serialized twice: once to compute the size of the message,
+/// then to write it after the header. Since each field is written
+/// separately, `writer` should be buffered, e.g. with [`std::io::BufWriter`].
+///
+/// If `value` does not serialize to the same number of bytes twice,
+/// [`Error::InvalidLength`] is returned after the message is written.
+#[cfg(feature = "std")]
+pub fn to_writer<W, T>(writer: &mut W, value: &T) -> Result<()>
+where
+    W: io::Write + ?Sized,
+    T: Serialize,
+{
+    let mut serializer = Serializer::new(LenCounter::default());
+    value.serialize(&mut serializer)?;
+    let header = serializer.create_header(0)?;
+    let expected = serializer.output.len;
+
+    writer.write_all(&header)?;
+
+    let mut serializer = Serializer::new(WriteOutput::new(&mut *writer));
+    value.serialize(&mut serializer)?;
+    let found = serializer.output.finish()?;
+
+    if found != expected {
+        return Err(Error::InvalidLength { expected, found });
+    }
+
+    Ok(())
+}
+
+/// Return the size of the serialized `value` excluding the 4-byte header,
+/// i.e. what the header written by [`to_bytes`] would hold, without
+/// allocating any buffer.
+pub fn serialized_size<T>(value: &T) -> Result<u32>
+where
+    T: Serialize,
+{
+    let mut serializer = Serializer::new(LenCounter::default());
+    value.serialize(&mut serializer)?;
+    serializer.create_header(0).map(u32::from_be_bytes)
+}
+
+/// Run the serialization of `value` without keeping the output, and
+/// return the size of its body, i.e. excluding the 4-byte header.
+///
+/// Return [`Error::TooLong`] as soon as the body would exceed `max_len`
+/// bytes, or any other error the serialization would fail with.
+pub fn validate<T>(value: &T, max_len: usize) -> Result<usize>
+where
+    T: Serialize,
+{
+    let mut serializer = Serializer::with_max_output(LenCounter::default(), max_len);
+    value.serialize(&mut serializer)?;
+    serializer.create_header(0)?;
+
+    Ok(serializer.output.len)
+}
+
 macro_rules! impl_for_serialize_primitive {
     ( $name:ident, $type:ty ) => {
         fn $name(self, v: $type) -> Result<()> {
-            self.extend_from_slice(&v.to_be_bytes());
-            Ok(())
+            self.begin_field();
+            let bytes = match self.endianness {
+                Endianness::Big => v.to_be_bytes(),
+                Endianness::Little => v.to_le_bytes(),
+            };
+            let result = self.extend_from_slice(&bytes);
+            self.end_field();
+
+            result
         }
     };
 }
 
-impl<'a, Container: SerOutput> ser::Serializer for &'a mut Serializer<Container> {
+impl<Container: SerOutput> ser::Serializer for &mut Serializer<Container> {
     type Ok = ();
     type Error = Error;
 
@@ -93,6 +686,9 @@ impl<'a, Container: SerOutput> ser::Serializer for &'a mut Serializer<Container>
     type SerializeTuple = Self;
     type SerializeTupleStruct = Self;
     type SerializeTupleVariant = Self;
+    #[cfg(feature = "map")]
+    type SerializeMap = MapSerializer<Self>;
+    #[cfg(not(feature = "map"))]
     type SerializeMap = Self;
     type SerializeStruct = Self;
     type SerializeStructVariant = Self;
@@ -102,22 +698,26 @@ impl<'a, Container: SerOutput> ser::Serializer for &'a mut Serializer<Container>
     }
 
     fn serialize_u8(self, v: u8) -> Result<()> {
-        self.push(v);
-        Ok(())
+        self.begin_field();
+        let result = self.push(v);
+        self.end_field();
+
+        result
     }
 
     fn serialize_i8(self, v: i8) -> Result<()> {
-        self.push(v as u8);
-        Ok(())
+        self.serialize_u8(v as u8)
     }
 
     impl_for_serialize_primitive!(serialize_i16, i16);
     impl_for_serialize_primitive!(serialize_i32, i32);
     impl_for_serialize_primitive!(serialize_i64, i64);
+    impl_for_serialize_primitive!(serialize_i128, i128);
 
     impl_for_serialize_primitive!(serialize_u16, u16);
     impl_for_serialize_primitive!(serialize_u32, u32);
     impl_for_serialize_primitive!(serialize_u64, u64);
+    impl_for_serialize_primitive!(serialize_u128, u128);
 
     impl_for_serialize_primitive!(serialize_f32, f32);
     impl_for_serialize_primitive!(serialize_f64, f64);
@@ -134,40 +734,45 @@ impl<'a, Container: SerOutput> ser::Serializer for &'a mut Serializer<Container>
         let bytes = v.as_bytes();
 
         let null_byte_counts = bytes.iter().copied().filter(is_null_byte).count();
+        if null_byte_counts != 0 && self.canonical {
+            return Err(Error::Unsupported(&"NUL byte in canonical mode"));
+        }
 
         let len = bytes.len() - null_byte_counts;
 
         // Reserve bytes
         self.reserve(4 + len);
 
-        self.serialize_usize(len)?;
-
-        if null_byte_counts == 0 {
-            self.extend_from_slice(v.as_bytes());
-        } else {
-            bytes
-                .split(is_null_byte)
-                .filter(|slice| !slice.is_empty())
-                .for_each(|slice| {
-                    self.extend_from_slice(slice);
-                });
-        }
+        self.begin_field();
+        let result = self.serialize_usize(len).and_then(|()| {
+            if null_byte_counts == 0 {
+                self.extend_from_slice(v.as_bytes())
+            } else {
+                bytes
+                    .split(is_null_byte)
+                    .filter(|slice| !slice.is_empty())
+                    .try_for_each(|slice| self.extend_from_slice(slice))
+            }
+        });
+        self.end_field();
 
-        Ok(())
+        result
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<()> {
         self.reserve(4 + v.len());
 
-        self.serialize_usize(v.len())?;
+        self.begin_field();
+        let result = self
+            .serialize_usize(v.len())
+            .and_then(|()| self.extend_from_slice(v));
+        self.end_field();
 
-        self.extend_from_slice(v);
-
-        Ok(())
+        result
     }
 
     fn serialize_none(self) -> Result<()> {
-        Ok(())
+        self.serialize_unit()
     }
 
     fn serialize_some<T>(self, value: &T) -> Result<()>
@@ -178,7 +783,11 @@ impl<'a, Container: SerOutput> ser::Serializer for &'a mut Serializer<Container>
     }
 
     fn serialize_unit(self) -> Result<()> {
-        Ok(())
+        if self.strict {
+            Err(Error::Unsupported(&"implicit omission"))
+        } else {
+            Ok(())
+        }
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
@@ -192,16 +801,26 @@ impl<'a, Container: SerOutput> ser::Serializer for &'a mut Serializer<Container>
         value.serialize(self)
     }
 
+    /// Sequence is encoded as its length (`u32`) followed by its elements.
+    ///
+    /// Only sequences with a known length are supported, since omitting
+    /// the length would produce output that cannot be deserialized.
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
-        if let Some(len) = len {
-            self.reserve(4 + len as usize);
+        let len = len.ok_or(Error::Unsupported(&"serialize_seq without length"))?;
+
+        self.reserve(4 + len);
 
-            self.serialize_usize(len)?;
+        self.begin_field();
+        if let Err(err) = self.serialize_usize(len) {
+            self.end_field();
+            return Err(err);
         }
+
         Ok(self)
     }
 
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        self.begin_field();
         Ok(self)
     }
 
@@ -236,8 +855,13 @@ impl<'a, Container: SerOutput> ser::Serializer for &'a mut Serializer<Container>
     where
         T: ?Sized + Serialize,
     {
-        self.serialize_unit_variant(name, variant_index, variant)?;
-        value.serialize(self)
+        self.begin_field();
+        let result = self
+            .serialize_unit_variant(name, variant_index, variant)
+            .and_then(|()| value.serialize(&mut *self));
+        self.end_field();
+
+        result
     }
 
     fn serialize_tuple_variant(
@@ -247,8 +871,12 @@ impl<'a, Container: SerOutput> ser::Serializer for &'a mut Serializer<Container>
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        self.serialize_unit_variant(name, variant_index, variant)?;
-        self.serialize_tuple(len)
+        let serializer = self.serialize_tuple(len)?;
+        if let Err(err) = serializer.serialize_unit_variant(name, variant_index, variant) {
+            serializer.end_field();
+            return Err(err);
+        }
+        Ok(serializer)
     }
 
     fn serialize_struct_variant(
@@ -258,8 +886,12 @@ impl<'a, Container: SerOutput> ser::Serializer for &'a mut Serializer<Container>
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        self.serialize_unit_variant(name, variant_index, variant)?;
-        self.serialize_tuple(len)
+        let serializer = self.serialize_tuple(len)?;
+        if let Err(err) = serializer.serialize_unit_variant(name, variant_index, variant) {
+            serializer.end_field();
+            return Err(err);
+        }
+        Ok(serializer)
     }
 
     #[cfg(feature = "is_human_readable")]
@@ -268,7 +900,37 @@ impl<'a, Container: SerOutput> ser::Serializer for &'a mut Serializer<Container>
         false
     }
 
+    /// Map is encoded as its length (`u32`) followed by key-value pairs.
+    ///
+    /// Only maps with a known length are supported.
+    ///
+    /// See [`Serializer::sort_maps`] for the order of entries.
+    #[cfg(feature = "map")]
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+        let len = len.ok_or(Error::Unsupported(&"serialize_map without length"))?;
+        if self.canonical && !self.sort_maps {
+            return Err(Error::Unsupported(&"unsorted map in canonical mode"));
+        }
+
+        self.begin_field();
+        if let Err(err) = self.serialize_usize(len) {
+            self.end_field();
+            return Err(err);
+        }
+
+        let entries = if self.sort_maps {
+            Some(Vec::with_capacity(len))
+        } else {
+            None
+        };
+        Ok(MapSerializer {
+            serializer: self,
+            entries,
+        })
+    }
+
     /// Unsupported
+    #[cfg(not(feature = "map"))]
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
         Err(Error::Unsupported(&"serialize_map"))
     }
@@ -288,6 +950,7 @@ macro_rules! impl_serialize_trait {
             }
 
             fn end(self) -> Result<()> {
+                self.end_field();
                 Ok(())
             }
         }
@@ -299,8 +962,88 @@ impl_serialize_trait!(SerializeTuple, serialize_element);
 impl_serialize_trait!(SerializeTupleStruct, serialize_field);
 impl_serialize_trait!(SerializeTupleVariant, serialize_field);
 
+/// Returned by [`ser::Serializer::serialize_map`].
+#[cfg(feature = "map")]
+#[derive(Debug)]
+pub struct MapSerializer<S> {
+    serializer: S,
+    /// Serialized key-value pairs, buffered for sorting if enabled.
+    entries: Option<Vec<(Vec<u8>, Vec<u8>)>>,
+}
+
+#[cfg(feature = "map")]
+impl<Container: SerOutput> Serializer<Container> {
+    /// Serialize `value` into a new buffer, with the same configuration.
+    fn serialize_to_vec<T>(&self, value: &T) -> Result<Vec<u8>>
+    where
+        T: ?Sized + Serialize,
+    {
+        let remaining = self.max_output.saturating_sub(self.len);
+        let mut serializer = Serializer::with_max_output(Vec::new(), remaining)
+            .strict(self.strict)
+            .canonical(self.canonical)
+            .endianness(self.endianness)
+            .sort_maps(true);
+        value.serialize(&mut serializer)?;
+        Ok(serializer.output)
+    }
+}
+
+#[cfg(feature = "map")]
+impl<Container: SerOutput> ser::SerializeMap for MapSerializer<&mut Serializer<Container>> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match &mut self.entries {
+            Some(entries) => {
+                let key = self.serializer.serialize_to_vec(key)?;
+                entries.push((key, Vec::new()));
+                Ok(())
+            }
+            None => key.serialize(&mut *self.serializer),
+        }
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match &mut self.entries {
+            Some(entries) => {
+                let value = self.serializer.serialize_to_vec(value)?;
+                let entry = entries.last_mut();
+                entry
+                    .expect("serialize_value called before serialize_key")
+                    .1 = value;
+                Ok(())
+            }
+            None => value.serialize(&mut *self.serializer),
+        }
+    }
+
+    fn end(self) -> Result<()> {
+        let serializer = self.serializer;
+        let result = self.entries.map_or(Ok(()), |mut entries| {
+            entries.sort_by(|(key1, _), (key2, _)| key1.cmp(key2));
+
+            entries.into_iter().try_for_each(|(key, value)| {
+                serializer.extend_from_slice(&key)?;
+                serializer.extend_from_slice(&value)
+            })
+        });
+        serializer.end_field();
+
+        result
+    }
+}
+
 /// Unsupported
-impl<'a, Container: SerOutput> ser::SerializeMap for &'a mut Serializer<Container> {
+#[cfg(not(feature = "map"))]
+impl<Container: SerOutput> ser::SerializeMap for &mut Serializer<Container> {
     type Ok = ();
     type Error = Error;
 
@@ -326,7 +1069,7 @@ impl<'a, Container: SerOutput> ser::SerializeMap for &'a mut Serializer<Containe
     }
 }
 
-impl<'a, Container: SerOutput> ser::SerializeStruct for &'a mut Serializer<Container> {
+impl<Container: SerOutput> ser::SerializeStruct for &mut Serializer<Container> {
     type Ok = ();
     type Error = Error;
 
@@ -338,10 +1081,11 @@ impl<'a, Container: SerOutput> ser::SerializeStruct for &'a mut Serializer<Conta
     }
 
     fn end(self) -> Result<()> {
+        self.end_field();
         Ok(())
     }
 }
-impl<'a, Container: SerOutput> ser::SerializeStructVariant for &'a mut Serializer<Container> {
+impl<Container: SerOutput> ser::SerializeStructVariant for &mut Serializer<Container> {
     type Ok = ();
     type Error = Error;
 
@@ -359,7 +1103,10 @@ impl<'a, Container: SerOutput> ser::SerializeStructVariant for &'a mut Serialize
 
 #[cfg(test)]
 mod tests {
-    use crate::{to_bytes, Serializer};
+    #[cfg(feature = "std")]
+    use super::to_writer;
+    use super::{serialized_size, to_slice, validate};
+    use crate::{to_bytes, Error, Serializer};
     use serde::{ser, Serialize};
     use std::convert::TryInto;
 
@@ -375,6 +1122,16 @@ mod tests {
             to_bytes(&0x1234567887654321_u64).unwrap(),
             [0, 0, 0, 8, 0x12, 0x34, 0x56, 0x78, 0x87, 0x65, 0x43, 0x21]
         );
+
+        // 128-bit integers are encoded as 16 bytes in big endian
+        let serialized = to_bytes(&0x0102030405060708090a0b0c0d0e0f10_u128).unwrap();
+        assert_eq!(serialized[..4], [0, 0, 0, 16]);
+        assert_eq!(serialized[4..], (1..=16).collect::<Vec<u8>>()[..]);
+
+        let serialized = to_bytes(&-2_i128).unwrap();
+        assert_eq!(serialized[..4], [0, 0, 0, 16]);
+        assert_eq!(serialized[4..19], [0xff; 15]);
+        assert_eq!(serialized[19], 0xfe);
     }
 
     #[test]
@@ -422,6 +1179,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_seq_without_len() {
+        struct Filtered;
+
+        impl Serialize for Filtered {
+            fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                // `Filter` does not have an exact size hint.
+                serializer.collect_seq((0..4_u32).filter(|i| i % 2 == 0))
+            }
+        }
+
+        assert!(matches!(
+            to_bytes(&Filtered),
+            Err(Error::Unsupported(&"serialize_seq without length"))
+        ));
+
+        let mut serializer: Serializer = Serializer::default();
+        assert!(matches!(
+            ser::Serializer::serialize_seq(&mut serializer, None),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(serializer.output.is_empty());
+    }
+
+    #[test]
+    fn test_fixed_array() {
+        let words = [0x01020304_u32; 8];
+        let serialized = to_bytes(&words).unwrap();
+        assert_eq!(serialized[..4], [0, 0, 0, 32]);
+        assert_eq!(serialized[4..], [1, 2, 3, 4].repeat(8)[..]);
+
+        let fingerprint: [u8; 32] = core::array::from_fn(|i| i as u8);
+        let serialized = to_bytes(&fingerprint).unwrap();
+        assert_eq!(serialized[..4], [0, 0, 0, 32]);
+        assert_eq!(serialized[4..], fingerprint);
+
+        // Unlike fixed arrays, `Vec` is prefixed with its length
+        let serialized = to_bytes(&fingerprint.to_vec()).unwrap();
+        assert_eq!(serialized[..8], [0, 0, 0, 36, 0, 0, 0, 32]);
+        assert_eq!(serialized[8..], fingerprint);
+    }
+
     #[test]
     fn test_tuple() {
         assert_eq!(
@@ -451,6 +1250,939 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_begin_end_len() {
+        let mut serializer: Serializer<Vec<u8>> = Serializer::default();
+
+        0x12_u8.serialize(&mut serializer).unwrap();
+
+        let outer = serializer.begin_len().unwrap();
+        0x34_u8.serialize(&mut serializer).unwrap();
+
+        let inner1 = serializer.begin_len().unwrap();
+        0x5678_u16.serialize(&mut serializer).unwrap();
+
+        let inner2 = serializer.begin_len().unwrap();
+        "ab".serialize(&mut serializer).unwrap();
+
+        // Finalize out of order
+        serializer.end_len(inner1).unwrap();
+        serializer.end_len(outer).unwrap();
+        serializer.end_len(inner2).unwrap();
+
+        assert_eq!(serializer.create_header(0).unwrap(), [0, 0, 0, 22]);
+        assert_eq!(
+            serializer.output,
+            [
+                0x12, // u8
+                0, 0, 0, 17,   // outer
+                0x34, // u8
+                0, 0, 0, 12, // inner1
+                0x56, 0x78, // u16
+                0, 0, 0, 6, // inner2
+                0, 0, 0, 2, b'a', b'b', // str
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_io_slices() {
+        let value = (0x1234_u16, "Hello, world!", vec![1_u8, 2, 3]);
+
+        let (body, header) = super::to_io_slices(&value).unwrap();
+        assert_eq!([&header[..], &body].concat(), to_bytes(&value).unwrap());
+    }
+
+    #[test]
+    fn test_undo_last() {
+        let mut serializer: Serializer = Serializer::default();
+
+        0x1234_u16.serialize(&mut serializer).unwrap();
+        "dropped".serialize(&mut serializer).unwrap();
+        serializer.undo_last(4 + 7);
+        0x12345678_u32.serialize(&mut serializer).unwrap();
+
+        let expected = to_bytes(&(0x1234_u16, 0x12345678_u32)).unwrap();
+        assert_eq!(serializer.create_header(0).unwrap(), expected[..4]);
+        assert_eq!(serializer.output, expected[4..]);
+
+        serializer.undo_last(0);
+        assert_eq!(serializer.output, expected[4..]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_undo_last_too_many() {
+        let mut serializer: Serializer = Serializer::default();
+        0x1234_u16.serialize(&mut serializer).unwrap();
+        serializer.undo_last(3);
+    }
+
+    #[test]
+    fn test_with_max_output() {
+        let mut serializer = Serializer::with_max_output(Vec::new(), 16);
+        (0x1234_u16, "Hello").serialize(&mut serializer).unwrap();
+        assert_eq!(
+            serializer.output,
+            to_bytes(&(0x1234_u16, "Hello")).unwrap()[4..]
+        );
+
+        // Exactly at the limit
+        0x12345678_u32.serialize(&mut serializer).unwrap();
+        0_u8.serialize(&mut serializer).unwrap();
+        assert_eq!(serializer.output.len(), 16);
+        assert!(matches!(
+            0_u8.serialize(&mut serializer),
+            Err(Error::TooLong)
+        ));
+
+        // Fails early, without writing or reserving the whole collection
+        let large = vec![0_u32; 1 << 20];
+        let mut serializer = Serializer::with_max_output(Vec::new(), 1024);
+        assert!(matches!(
+            large.serialize(&mut serializer),
+            Err(Error::TooLong)
+        ));
+        assert!(serializer.output.len() <= 1024);
+        assert!(serializer.output.capacity() <= 1024);
+
+        // Limit applies to the counter
+        serializer.reset_counter();
+        serializer.output.clear();
+        vec![0_u8; 1020].serialize(&mut serializer).unwrap();
+    }
+
+    #[test]
+    fn test_write_nested_message() {
+        use crate::{from_bytes, from_bytes_framed};
+
+        let inner = to_bytes(&(0x1234_u16, "tunneled")).unwrap();
+
+        let mut serializer: Serializer = Serializer::default();
+        1_u32.serialize(&mut serializer).unwrap();
+        serializer.write_nested_message(&inner).unwrap();
+        2_u8.serialize(&mut serializer).unwrap();
+        let body = serializer.output;
+
+        let ((id, nested, flag), trailing) = from_bytes::<(u32, &[u8], u8)>(&body).unwrap();
+        assert_eq!((id, nested, flag), (1, &inner[..], 2));
+        assert!(trailing.is_empty());
+
+        let (decoded, rest) = from_bytes_framed::<(u16, &str)>(nested).unwrap();
+        assert_eq!(decoded, (0x1234, "tunneled"));
+        assert!(rest.is_empty());
+
+        // Invalid frames are rejected without writing anything
+        let mut serializer: Serializer = Serializer::default();
+        assert!(matches!(
+            serializer.write_nested_message(&inner[..inner.len() - 1]),
+            Err(Error::InvalidLength { expected, found }) if expected == found + 1
+        ));
+        assert!(matches!(
+            serializer.write_nested_message(&[0, 0]),
+            Err(Error::Eof)
+        ));
+        assert!(serializer.output.is_empty());
+    }
+
+    #[test]
+    fn test_write_raw_framed() {
+        use crate::from_bytes;
+
+        let cert = to_bytes(&(0x1234_u16, "ssh-ed25519")).unwrap();
+        let cert = &cert[4..];
+
+        let mut serializer: Serializer = Serializer::default();
+        ser::Serializer::serialize_u32(&mut serializer, 0x10).unwrap();
+        serializer.write_framed(cert).unwrap();
+        "user@host".serialize(&mut serializer).unwrap();
+        serializer.write_raw(&[0xaa, 0xbb]).unwrap();
+        true.serialize(&mut serializer).unwrap();
+
+        let header = serializer.create_header(0).unwrap();
+        let body = serializer.output;
+        assert_eq!(u32::from_be_bytes(header) as usize, body.len());
+
+        let mut expected = vec![0, 0, 0, 0x10];
+        expected.extend_from_slice(&(cert.len() as u32).to_be_bytes());
+        expected.extend_from_slice(cert);
+        expected.extend_from_slice(b"\0\0\0\x09user@host");
+        expected.extend_from_slice(&[0xaa, 0xbb, 0, 0, 0, 1]);
+        assert_eq!(body, expected);
+
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Message<'a> {
+            id: u32,
+            cert: &'a [u8],
+            user: &'a str,
+            raw: [u8; 2],
+            flag: bool,
+        }
+
+        let (message, trailing) = from_bytes::<Message>(&body).unwrap();
+        assert_eq!(
+            message,
+            Message {
+                id: 0x10,
+                cert,
+                user: "user@host",
+                raw: [0xaa, 0xbb],
+                flag: true,
+            }
+        );
+        assert!(trailing.is_empty());
+
+        // The embedded blob decodes on its own.
+        let (decoded, _) = from_bytes::<(u16, &str)>(message.cert).unwrap();
+        assert_eq!(decoded, (0x1234, "ssh-ed25519"));
+    }
+
+    #[test]
+    fn test_begin_end_seq() {
+        let mut serializer: Serializer = Serializer::default();
+
+        0x12_u8.serialize(&mut serializer).unwrap();
+
+        let token = serializer.begin_seq().unwrap();
+        let mut count = 0;
+        for s in ["a", "", "b", "cd"].iter().filter(|s| !s.is_empty()) {
+            s.serialize(&mut serializer).unwrap();
+            count += 1;
+        }
+        serializer.end_seq(token, count);
+
+        0x34_u8.serialize(&mut serializer).unwrap();
+
+        let expected = to_bytes(&(0x12_u8, vec!["a", "b", "cd"], 0x34_u8)).unwrap();
+        assert_eq!(serializer.create_header(0).unwrap(), expected[..4]);
+        assert_eq!(serializer.output, expected[4..]);
+
+        // Empty sequence
+        let mut serializer: Serializer = Serializer::default();
+        let token = serializer.begin_seq().unwrap();
+        serializer.end_seq(token, 0);
+        assert_eq!(serializer.output, to_bytes(&Vec::<u8>::new()).unwrap()[4..]);
+    }
+
+    #[test]
+    fn test_serialize_value_body() {
+        let value = (0x1234_u16, "Hello, world!");
+
+        let mut serializer: Serializer = Serializer::default();
+        assert_eq!(
+            serializer.serialize_value_body(&value).unwrap(),
+            &to_bytes(&value).unwrap()[4..]
+        );
+
+        // Only the bytes of the value are returned
+        let body = serializer.serialize_value_body(&1_u32).unwrap();
+        assert_eq!(body, [0, 0, 0, 1]);
+
+        let expected = to_bytes(&(value, 1_u32)).unwrap();
+        assert_eq!(serializer.create_header(0).unwrap(), expected[..4]);
+        assert_eq!(serializer.output, expected[4..]);
+    }
+
+    #[test]
+    fn test_take_output() {
+        let mut serializer: Serializer = Serializer::default();
+
+        (1_u8, "Hello").serialize(&mut serializer).unwrap();
+        assert_eq!(
+            serializer.take_output(),
+            to_bytes(&(1_u8, "Hello")).unwrap()[4..]
+        );
+        assert!(serializer.output.is_empty());
+        assert_eq!(serializer.create_header(0).unwrap(), [0, 0, 0, 0]);
+
+        2_u32.serialize(&mut serializer).unwrap();
+        assert_eq!(serializer.create_header(0).unwrap(), 4_u32.to_be_bytes());
+        assert_eq!(serializer.take_output(), [0, 0, 0, 2]);
+    }
+
+    #[test]
+    fn test_endianness() {
+        let mut serializer = Serializer::new(Vec::new()).endianness(crate::Endianness::Little);
+        (0x1234_u16, "ab", vec![true], -2_i64, 1.0_f32)
+            .serialize(&mut serializer)
+            .unwrap();
+
+        assert_eq!(serializer.create_header(0).unwrap(), [28, 0, 0, 0]);
+        assert_eq!(
+            serializer.output,
+            [
+                0x34, 0x12, // u16
+                2, 0, 0, 0, b'a', b'b', // str
+                1, 0, 0, 0, 1, 0, 0, 0, // seq
+                0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // i64
+                0, 0, 0x80, 0x3f, // f32
+            ]
+        );
+
+        // Placeholders of sequences with unknown length are backpatched too
+        let mut serializer = Serializer::new(Vec::new()).endianness(crate::Endianness::Little);
+        let token = serializer.begin_seq().unwrap();
+        serializer.end_seq(token, 3);
+        assert_eq!(serializer.output, [3, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_write_char_checked() {
+        let mut serializer: Serializer = Serializer::default();
+        serializer.write_char_checked(0x1F980).unwrap();
+        assert_eq!(serializer.output, to_bytes(&'\u{1F980}').unwrap()[4..]);
+
+        for &invalid in &[0xD800_u32, 0x110000, u32::MAX] {
+            let mut serializer: Serializer = Serializer::default();
+            assert!(matches!(
+                serializer.write_char_checked(invalid),
+                Err(Error::InvalidChar(v)) if v == invalid
+            ));
+            assert!(serializer.output.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_write_socket_addr() {
+        use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+
+        let mut serializer: Serializer = Serializer::default();
+        serializer
+            .write_ip(IpAddr::V6(Ipv6Addr::LOCALHOST))
+            .unwrap();
+        serializer
+            .write_socket_addr(SocketAddr::from(([127, 0, 0, 1], 22)))
+            .unwrap();
+
+        let expected = to_bytes(&("::1", "127.0.0.1", 22_u32)).unwrap();
+        assert_eq!(serializer.create_header(0).unwrap(), expected[..4]);
+        assert_eq!(serializer.output, expected[4..]);
+    }
+
+    #[test]
+    fn test_to_slice() {
+        let value = (0x1234_u16, "Hello, world!");
+        let expected = to_bytes(&value).unwrap();
+
+        let mut buffer = [0xff_u8; 32];
+        assert_eq!(to_slice(&mut buffer, &value).unwrap(), expected.len());
+        assert_eq!(buffer[..expected.len()], expected);
+        assert!(buffer[expected.len()..].iter().all(|byte| *byte == 0xff));
+
+        let mut buffer = vec![0_u8; expected.len()];
+        assert_eq!(to_slice(&mut buffer, &value).unwrap(), expected.len());
+        assert_eq!(buffer, expected);
+
+        let mut buffer = [0_u8; 8];
+        assert!(matches!(
+            to_slice(&mut buffer, &value),
+            Err(Error::BufferTooSmall { needed }) if needed == expected.len()
+        ));
+
+        let mut buffer = [0_u8; 2];
+        assert!(matches!(
+            to_slice(&mut buffer, &value),
+            Err(Error::BufferTooSmall { needed }) if needed == expected.len()
+        ));
+    }
+
+    #[test]
+    fn test_to_bytes_sized() {
+        use super::{to_bytes_sized, SizeHint};
+
+        #[derive(Serialize)]
+        struct Fixed {
+            a: u32,
+            b: u64,
+            c: [u16; 4],
+        }
+
+        impl SizeHint for Fixed {
+            fn size_hint(&self) -> usize {
+                4 + 8 + 2 * 4
+            }
+        }
+
+        #[derive(Serialize)]
+        struct NoHint(u32);
+
+        impl SizeHint for NoHint {}
+
+        let value = Fixed {
+            a: 1,
+            b: 2,
+            c: [3, 4, 5, 6],
+        };
+        let serialized = to_bytes_sized(&value).unwrap();
+        assert_eq!(serialized, to_bytes(&value).unwrap());
+        // Reserved exactly once, with no reallocation.
+        assert_eq!(serialized.len(), 4 + value.size_hint());
+        assert_eq!(serialized.capacity(), serialized.len());
+
+        let value = NoHint(0x1234);
+        assert_eq!(value.size_hint(), 0);
+        assert_eq!(to_bytes_sized(&value).unwrap(), to_bytes(&value).unwrap());
+    }
+
+    #[test]
+    fn test_metered_output() {
+        use crate::{MeteredOutput, SerOutput, SerOutputBuf};
+
+        let value1 = (0x1234_u16, "Hello, world!");
+        let value2 = [0x12345678_u32; 3];
+
+        let mut serializer = Serializer::new(MeteredOutput::new(Vec::new()));
+
+        value1.serialize(&mut serializer).unwrap();
+        let len1 = to_bytes(&value1).unwrap().len() - 4;
+        assert_eq!(serializer.output.bytes_written(), len1);
+
+        value2.serialize(&mut serializer).unwrap();
+        let len2 = to_bytes(&value2).unwrap().len() - 4;
+        assert_eq!(serializer.output.bytes_written(), len1 + len2);
+
+        assert_eq!(serializer.output.get_ref().len(), len1 + len2);
+
+        // Bytes already in the inner buffer are not counted
+        let mut output = MeteredOutput::new(vec![0; 4]);
+        output.extend_from_slice(&[1, 2]);
+        output.truncate(5);
+        assert_eq!(output.bytes_written(), 1);
+        output.truncate(0);
+        assert_eq!(output.bytes_written(), 0);
+        assert!(output.get_ref().is_empty());
+    }
+
+    #[test]
+    fn test_serialize_framed() {
+        struct Invalid;
+
+        impl Serialize for Invalid {
+            fn serialize<S: ser::Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                use ser::SerializeTuple;
+
+                let mut tuple = serializer.serialize_tuple(2)?;
+                tuple.serialize_element(&1_u32)?;
+                Err(ser::Error::custom("invalid"))
+            }
+        }
+
+        let mut serializer: Serializer = Serializer::default();
+
+        let frame = serializer.serialize_framed(&(1_u32, "Hello")).unwrap();
+        assert_eq!(frame, &to_bytes(&(1_u32, "Hello")).unwrap()[..]);
+
+        assert!(matches!(
+            serializer.serialize_framed(&Invalid),
+            Err(Error::Message(_))
+        ));
+
+        let frame = serializer.serialize_framed(&2_u8).unwrap();
+        assert_eq!(frame, &to_bytes(&2_u8).unwrap()[..]);
+
+        let expected = [
+            to_bytes(&(1_u32, "Hello")).unwrap(),
+            to_bytes(&2_u8).unwrap(),
+        ]
+        .concat();
+        assert_eq!(serializer.output, expected);
+        assert_eq!(
+            serializer.create_header(0).unwrap(),
+            (expected.len() as u32).to_be_bytes()
+        );
+
+        // Same header as `create_header`
+        let mut serializer: Serializer = Serializer::default().include_header_in_len(true);
+        let frame = serializer.serialize_framed(&(1_u32, "Hello")).unwrap();
+        assert_eq!(frame[..4], 17_u32.to_be_bytes());
+        assert_eq!(frame[4..], to_bytes(&(1_u32, "Hello")).unwrap()[4..]);
+
+        // The failing value is not counted as a field
+        let mut serializer: Serializer = Serializer::default().enable_field_count_trailer(true);
+        serializer.serialize_framed(&1_u8).unwrap();
+        assert!(serializer.serialize_framed(&Invalid).is_err());
+        serializer.finish_header(0).unwrap();
+        assert_eq!(serializer.output, [0, 0, 0, 1, 1, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_len_counter() {
+        use crate::LenCounter;
+
+        let value = (1_u32, "Hello", vec![2_u16, 3]);
+        let expected = to_bytes(&value).unwrap();
+
+        let mut counter = Serializer::new(LenCounter::new());
+        assert_eq!(counter.output.len, 0);
+        value.serialize(&mut counter).unwrap();
+        assert_eq!(counter.output.len, expected.len() - 4);
+
+        // Header first, then the body, into a single buffer
+        let mut buffer = Vec::with_capacity(4 + counter.output.len);
+        buffer.extend_from_slice(&counter.create_header(0).unwrap());
+        value.serialize(&mut Serializer::new(&mut buffer)).unwrap();
+
+        assert_eq!(buffer, expected);
+        assert_eq!(buffer.capacity(), buffer.len());
+    }
+
+    #[test]
+    fn test_write_header_into() {
+        let mut serializer: Serializer = Serializer::default();
+        (1_u32, "Hello").serialize(&mut serializer).unwrap();
+
+        let mut header = [0xff_u8; 6];
+        serializer.write_header_into(&mut header, 2).unwrap();
+        assert_eq!(header, [0, 0, 0, 15, 0xff, 0xff]);
+
+        assert!(matches!(
+            serializer.write_header_into(&mut header[..3], 0),
+            Err(Error::BufferTooSmall { needed: 4 })
+        ));
+        assert_eq!(header, [0, 0, 0, 15, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn test_with_capacity() {
+        use super::to_bytes_with_capacity;
+
+        let value = (1_u32, "Hello");
+        let serialized = to_bytes_with_capacity(&value, 64).unwrap();
+        assert_eq!(serialized, to_bytes(&value).unwrap());
+        assert!(serialized.capacity() >= 64);
+
+        // A smaller capacity only means reallocating
+        assert_eq!(
+            to_bytes_with_capacity(&value, 0).unwrap(),
+            to_bytes(&value).unwrap()
+        );
+
+        let mut serializer = Serializer::with_capacity(64);
+        assert!(serializer.output.capacity() >= 64);
+        value.serialize(&mut serializer).unwrap();
+        assert_eq!(serializer.output, to_bytes(&value).unwrap()[4..]);
+    }
+
+    #[cfg(feature = "smallvec")]
+    #[test]
+    fn test_smallvec_output() {
+        use smallvec::SmallVec;
+
+        #[derive(Serialize)]
+        struct Request<'a> {
+            id: u32,
+            path: &'a str,
+        }
+
+        let value = Request {
+            id: 1,
+            path: "/tmp/mux",
+        };
+
+        let mut serializer = Serializer::new(SmallVec::<[u8; 64]>::new());
+        value.serialize(&mut serializer).unwrap();
+        let header = serializer.create_header(0).unwrap();
+
+        assert!(!serializer.output.spilled());
+        assert_eq!(
+            [&header[..], &serializer.output[..]].concat(),
+            to_bytes(&value).unwrap()
+        );
+
+        // Spill onto the heap once it does not fit
+        let mut serializer = Serializer::new(SmallVec::<[u8; 4]>::new());
+        value.serialize(&mut serializer).unwrap();
+        assert!(serializer.output.spilled());
+        assert_eq!(serializer.output[..], to_bytes(&value).unwrap()[4..]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_cursor_output() {
+        use std::io::{self, Cursor};
+
+        let mut serializer = Serializer::new(Cursor::new(Vec::new()));
+
+        // Leave room for the header, then backfill it
+        serializer.output.set_position(4);
+        (1_u32, "Hello").serialize(&mut serializer).unwrap();
+        let header = serializer.create_header(0).unwrap();
+
+        serializer.output.set_position(0);
+        io::Write::write_all(&mut serializer.output, &header).unwrap();
+
+        let output = serializer.output.into_inner();
+        assert_eq!(output, to_bytes(&(1_u32, "Hello")).unwrap());
+    }
+
+    #[test]
+    fn test_strict() {
+        use std::marker::PhantomData;
+
+        fn serialize_strict<T: Serialize>(value: &T) -> Result<Vec<u8>, Error> {
+            let mut serializer = Serializer::default().strict(true);
+            value.serialize(&mut serializer)?;
+            Ok(serializer.output)
+        }
+
+        assert_eq!(serialize_strict(&Some(1_u8)).unwrap(), [1]);
+        assert_eq!(serialize_strict(&(1_u8, 2_u8)).unwrap(), [1, 2]);
+
+        assert!(matches!(
+            serialize_strict(&None::<u8>),
+            Err(Error::Unsupported(&"implicit omission"))
+        ));
+        assert!(matches!(
+            serialize_strict(&()),
+            Err(Error::Unsupported(&"implicit omission"))
+        ));
+        assert!(matches!(
+            serialize_strict(&(1_u8, PhantomData::<u8>)),
+            Err(Error::Unsupported(&"implicit omission"))
+        ));
+
+        // Omission is still allowed by default
+        assert_eq!(to_bytes(&(1_u8, None::<u8>, ())).unwrap(), [0, 0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn test_canonical() {
+        fn serialize_canonical<T: Serialize>(value: &T) -> Result<Vec<u8>, Error> {
+            let mut serializer = Serializer::default().canonical(true);
+            value.serialize(&mut serializer)?;
+            Ok(serializer.output)
+        }
+
+        let value = (1_u32, "Hello", vec![Some(2_u8), None], b"\0\0".to_vec());
+        let serialized = serialize_canonical(&value).unwrap();
+        assert_eq!(serialized, to_bytes(&value).unwrap()[4..]);
+        for _ in 0..4 {
+            assert_eq!(serialize_canonical(&value).unwrap(), serialized);
+        }
+
+        // NUL bytes in bytes are kept as-is, but in str they are rejected.
+        assert!(matches!(
+            serialize_canonical(&"a\0b"),
+            Err(Error::Unsupported(&"NUL byte in canonical mode"))
+        ));
+        assert_eq!(to_bytes(&"a\0b").unwrap(), to_bytes(&"ab").unwrap());
+    }
+
+    #[cfg(feature = "map")]
+    #[test]
+    fn test_canonical_map() {
+        use std::collections::HashMap;
+
+        let map: HashMap<_, _> = (0..32_u32).map(|i| (i, i.to_string())).collect();
+
+        let mut serializer: Serializer = Serializer::default().canonical(true);
+        assert!(matches!(
+            map.serialize(&mut serializer),
+            Err(Error::Unsupported(&"unsorted map in canonical mode"))
+        ));
+
+        let serialize = |map: &HashMap<u32, String>| {
+            let mut serializer = Serializer::new(Vec::new()).canonical(true).sort_maps(true);
+            map.serialize(&mut serializer).unwrap();
+            serializer.output
+        };
+
+        let serialized = serialize(&map);
+        for _ in 0..4 {
+            let copy: HashMap<_, _> = map.clone().into_iter().collect();
+            assert_eq!(serialize(&copy), serialized);
+        }
+
+        // Keys of nested maps are checked as well
+        let nested: HashMap<String, u8> = vec![("a\0".to_owned(), 1)].into_iter().collect();
+        let mut serializer: Serializer = Serializer::default().canonical(true).sort_maps(true);
+        assert!(matches!(
+            vec![nested].serialize(&mut serializer),
+            Err(Error::Unsupported(&"NUL byte in canonical mode"))
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_to_writer() {
+        let value = (0x1234_u16, "Hello, world!", vec![1_u32, 2]);
+
+        let mut output = Vec::new();
+        to_writer(&mut output, &value).unwrap();
+        assert_eq!(output, to_bytes(&value).unwrap());
+
+        let mut buffer = [0_u8; 8];
+        let mut writer = &mut buffer[..];
+        assert!(matches!(
+            to_writer(&mut writer, &value),
+            Err(Error::IoError(err)) if err.kind() == std::io::ErrorKind::WriteZero
+        ));
+
+        // Errors are reported before anything is written
+        struct Invalid;
+
+        impl Serialize for Invalid {
+            fn serialize<S: ser::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+                Err(ser::Error::custom("invalid"))
+            }
+        }
+
+        let mut output = Vec::new();
+        assert!(matches!(
+            to_writer(&mut output, &(1_u8, Invalid)),
+            Err(Error::Message(_))
+        ));
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_serialized_size() {
+        fn check<T: Serialize>(value: &T) {
+            let serialized = to_bytes(value).unwrap();
+            assert_eq!(
+                serialized_size(value).unwrap().to_be_bytes(),
+                serialized[..4]
+            );
+        }
+
+        check(&());
+        check(&(0x1234_u16, "Hello, world!", vec![1_u32, 2]));
+        check(&(Some(1_u8), None::<u32>, 'a', true));
+        assert_eq!(serialized_size(&"Hello").unwrap(), 9);
+    }
+
+    #[test]
+    fn test_validate() {
+        let value = (1_u32, "Hello, world!", vec![1_u16, 2, 3]);
+        let len = to_bytes(&value).unwrap().len() - 4;
+
+        assert_eq!(validate(&value, len).unwrap(), len);
+        assert_eq!(validate(&value, usize::MAX).unwrap(), len);
+        assert!(matches!(validate(&value, len - 1), Err(Error::TooLong)));
+
+        // Errors from the Serialize logic are reported as well
+        struct Invalid;
+
+        impl Serialize for Invalid {
+            fn serialize<S: ser::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+                Err(ser::Error::custom("invalid"))
+            }
+        }
+
+        assert!(matches!(
+            validate(&(1_u8, Invalid), usize::MAX),
+            Err(Error::Message(_))
+        ));
+    }
+
+    #[test]
+    fn test_include_header_in_len() {
+        let value = (1_u32, "Hello");
+
+        let mut serializer: Serializer = Serializer::default();
+        value.serialize(&mut serializer).unwrap();
+        assert_eq!(serializer.create_header(0).unwrap(), 13_u32.to_be_bytes());
+        assert_eq!(serializer.create_header(2).unwrap(), 15_u32.to_be_bytes());
+        assert_eq!(
+            serializer.create_header(0).unwrap(),
+            to_bytes(&value).unwrap()[..4]
+        );
+
+        let mut serializer: Serializer = Serializer::default().include_header_in_len(true);
+        value.serialize(&mut serializer).unwrap();
+        assert_eq!(serializer.create_header(0).unwrap(), 17_u32.to_be_bytes());
+        assert_eq!(serializer.create_header(2).unwrap(), 19_u32.to_be_bytes());
+
+        serializer.reset_counter();
+        assert_eq!(serializer.create_header(0).unwrap(), 4_u32.to_be_bytes());
+    }
+
+    #[test]
+    fn test_field_count_trailer() {
+        #[derive(Serialize)]
+        struct S {
+            a: u8,
+            b: Vec<u16>,
+        }
+
+        #[derive(Serialize)]
+        enum E {
+            A(u8, u8),
+            B { x: u8 },
+        }
+
+        let mut serializer: Serializer = Serializer::default().enable_field_count_trailer(true);
+        serializer.output.resize(4, 0);
+
+        S {
+            a: 1,
+            b: vec![2, 3],
+        }
+        .serialize(&mut serializer)
+        .unwrap();
+        "s".serialize(&mut serializer).unwrap();
+        None::<u8>.serialize(&mut serializer).unwrap();
+        E::A(4, 5).serialize(&mut serializer).unwrap();
+        E::B { x: 6 }.serialize(&mut serializer).unwrap();
+        serializer.write_raw(&[7]).unwrap();
+
+        let header = serializer.finish_header(0).unwrap();
+        // The trailer is only appended once
+        assert_eq!(serializer.finish_header(0).unwrap(), header);
+        let output = serializer.output;
+
+        assert_eq!(header, (output.len() as u32 - 4).to_be_bytes());
+        assert_eq!(output[output.len() - 4..], 5_u32.to_be_bytes());
+
+        // Absent by default
+        let mut serializer: Serializer = Serializer::default();
+        (1_u8, 2_u8).serialize(&mut serializer).unwrap();
+        assert_eq!(serializer.finish_header(0).unwrap(), 2_u32.to_be_bytes());
+        assert_eq!(serializer.output, [1, 2]);
+    }
+
+    #[test]
+    fn test_field_count_trailer_after_error() {
+        let mut serializer: Serializer =
+            Serializer::with_max_output(Vec::new(), 10).enable_field_count_trailer(true);
+
+        // Fails after writing the length
+        assert!(matches!(
+            "longer than max_output".serialize(&mut serializer),
+            Err(Error::TooLong)
+        ));
+        // Fails within an element
+        assert!(matches!(
+            (1_u32, "too long").serialize(&mut serializer),
+            Err(Error::TooLong)
+        ));
+
+        serializer.output.clear();
+        serializer.reset_counter();
+
+        1_u8.serialize(&mut serializer).unwrap();
+        (2_u8, 3_u8).serialize(&mut serializer).unwrap();
+        assert_eq!(serializer.finish_header(0).unwrap(), 7_u32.to_be_bytes());
+        assert_eq!(serializer.output, [1, 2, 3, 0, 0, 0, 2]);
+    }
+
+    #[cfg(feature = "map")]
+    #[test]
+    fn test_map() {
+        use std::collections::BTreeMap;
+
+        let map = BTreeMap::from([(2_u32, "b".to_owned()), (1, "a".to_owned())]);
+
+        assert_eq!(
+            to_bytes(&map).unwrap(),
+            [
+                0, 0, 0, 22, // Header
+                0, 0, 0, 2, // Number of entries
+                0, 0, 0, 1, 0, 0, 0, 1, b'a', // 1 => "a"
+                0, 0, 0, 2, 0, 0, 0, 1, b'b', // 2 => "b"
+            ]
+        );
+    }
+
+    #[cfg(feature = "map")]
+    #[test]
+    fn test_sort_maps() {
+        use std::collections::{BTreeMap, HashMap};
+
+        let entries: Vec<_> = (0..32_u32).map(|i| (i, i.to_string())).collect();
+
+        let serialize = |map: &HashMap<u32, String>| {
+            let mut serializer = Serializer::new(Vec::new()).sort_maps(true);
+            map.serialize(&mut serializer).unwrap();
+            serializer.output
+        };
+
+        let map1: HashMap<_, _> = entries.iter().cloned().collect();
+        let map2: HashMap<_, _> = entries.iter().rev().cloned().collect();
+
+        let serialized = serialize(&map1);
+        assert_eq!(serialized, serialize(&map2));
+
+        // Same as the map sorted by key
+        let sorted: BTreeMap<_, _> = entries.into_iter().collect();
+        assert_eq!(serialized, to_bytes(&sorted).unwrap()[4..]);
+
+        // Maps inside other values are sorted as well
+        let mut serializer = Serializer::new(Vec::new()).sort_maps(true);
+        vec![map1, map2].serialize(&mut serializer).unwrap();
+
+        let expected = to_bytes(&vec![&sorted, &sorted]).unwrap();
+        assert_eq!(serializer.create_header(0).unwrap(), expected[..4]);
+        assert_eq!(serializer.output, expected[4..]);
+    }
+
+    #[cfg(feature = "map")]
+    #[test]
+    fn test_sort_maps_little_endian() {
+        use std::collections::BTreeMap;
+
+        let map: BTreeMap<u32, u32> = [(1, 2), (256, 3)].iter().copied().collect();
+
+        let mut serializer = Serializer::new(Vec::new())
+            .endianness(crate::Endianness::Little)
+            .sort_maps(true);
+        map.serialize(&mut serializer).unwrap();
+
+        // Sorted by the little endian bytes of the keys
+        let expected: Vec<u8> = [2_u32, 256, 3, 1, 2]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        assert_eq!(serializer.output, expected);
+    }
+
+    #[cfg(not(feature = "map"))]
+    #[test]
+    fn test_map_unsupported() {
+        use std::collections::BTreeMap;
+
+        let map: BTreeMap<u32, String> = BTreeMap::new();
+        assert!(matches!(to_bytes(&map), Err(Error::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_newtype_struct() {
+        #[derive(Serialize)]
+        struct Id(u32);
+
+        #[derive(Serialize)]
+        struct Name(String);
+
+        #[derive(Serialize)]
+        struct Pair((u8, u16));
+
+        #[derive(Serialize)]
+        struct A(B);
+
+        #[derive(Serialize)]
+        struct B(u32);
+
+        assert_eq!(
+            to_bytes(&Id(0x1234)).unwrap(),
+            to_bytes(&0x1234_u32).unwrap()
+        );
+        assert_eq!(
+            to_bytes(&Name("Hello".to_owned())).unwrap(),
+            to_bytes(&"Hello").unwrap()
+        );
+        assert_eq!(
+            to_bytes(&Pair((1, 0x0203))).unwrap(),
+            to_bytes(&(1_u8, 0x0203_u16)).unwrap()
+        );
+        assert_eq!(
+            to_bytes(&A(B(0x1234))).unwrap(),
+            to_bytes(&0x1234_u32).unwrap()
+        );
+    }
+
     #[test]
     fn test_enum() {
         use ser::Serializer as SerdeSerializerTrait;
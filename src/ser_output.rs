@@ -1,3 +1,9 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::{Error, Result};
+
 /// A trait for which can be used to store serialized output.
 pub trait SerOutput {
     fn extend_from_slice(&mut self, other: &[u8]);
@@ -10,6 +16,16 @@ pub trait SerOutput {
     fn reserve(&mut self, additional: usize);
 }
 
+/// A [`SerOutput`] that allows accessing and modifying the bytes already
+/// written, e.g. for backpatching a length.
+pub trait SerOutputBuf: SerOutput {
+    fn as_slice(&self) -> &[u8];
+    fn as_mut_slice(&mut self) -> &mut [u8];
+
+    /// Shorten the output to `len` bytes, no-op if it is already shorter.
+    fn truncate(&mut self, len: usize);
+}
+
 impl<T: SerOutput + ?Sized> SerOutput for &mut T {
     fn extend_from_slice(&mut self, other: &[u8]) {
         (*self).extend_from_slice(other)
@@ -24,6 +40,20 @@ impl<T: SerOutput + ?Sized> SerOutput for &mut T {
     }
 }
 
+impl<T: SerOutputBuf + ?Sized> SerOutputBuf for &mut T {
+    fn as_slice(&self) -> &[u8] {
+        (**self).as_slice()
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        (**self).as_mut_slice()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        (**self).truncate(len)
+    }
+}
+
 impl SerOutput for Vec<u8> {
     fn extend_from_slice(&mut self, other: &[u8]) {
         self.extend_from_slice(other)
@@ -38,6 +68,43 @@ impl SerOutput for Vec<u8> {
     }
 }
 
+impl SerOutputBuf for Vec<u8> {
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.truncate(len)
+    }
+}
+
+/// Write at the position of the cursor, overwriting any byte already
+/// there and advancing it, e.g. to interleave framed messages at specific
+/// offsets.
+#[cfg(feature = "std")]
+impl SerOutput for io::Cursor<Vec<u8>> {
+    fn extend_from_slice(&mut self, other: &[u8]) {
+        io::Write::write_all(self, other).expect("Writing to Cursor<Vec<u8>> never fails")
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.extend_from_slice(&[byte])
+    }
+
+    /// Only reserve what is written past the end of the vec.
+    fn reserve(&mut self, additional: usize) {
+        let end = (self.position() as usize).saturating_add(additional);
+        let len = self.get_ref().len();
+        if end > len {
+            self.get_mut().reserve(end - len);
+        }
+    }
+}
+
 #[cfg(feature = "bytes")]
 impl SerOutput for bytes::BytesMut {
     fn extend_from_slice(&mut self, other: &[u8]) {
@@ -52,3 +119,253 @@ impl SerOutput for bytes::BytesMut {
         self.reserve(additional);
     }
 }
+
+#[cfg(feature = "bytes")]
+impl SerOutputBuf for bytes::BytesMut {
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.truncate(len)
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl<A: smallvec::Array<Item = u8>> SerOutput for smallvec::SmallVec<A> {
+    fn extend_from_slice(&mut self, other: &[u8]) {
+        self.extend_from_slice(other)
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.push(byte)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.reserve(additional);
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl<A: smallvec::Array<Item = u8>> SerOutputBuf for smallvec::SmallVec<A> {
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.truncate(len)
+    }
+}
+
+/// [`SerOutput`] writing into a fixed-size buffer, e.g. a mmaped region.
+///
+/// Once the buffer overflows, no more bytes are written but the required
+/// length is still tracked, which is reported by [`SliceWriter::finish`].
+#[derive(Debug)]
+pub struct SliceWriter<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceWriter<'a> {
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self { buffer, len: 0 }
+    }
+
+    /// Number of bytes required to hold everything written so far,
+    /// which can be larger than the buffer.
+    pub fn required_len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_overflowed(&self) -> bool {
+        self.len > self.buffer.len()
+    }
+
+    /// Return the number of bytes written, or [`Error::BufferTooSmall`]
+    /// if the buffer overflowed.
+    pub fn finish(self) -> Result<usize> {
+        if self.is_overflowed() {
+            Err(Error::BufferTooSmall { needed: self.len })
+        } else {
+            Ok(self.len)
+        }
+    }
+}
+
+impl SerOutput for SliceWriter<'_> {
+    fn extend_from_slice(&mut self, other: &[u8]) {
+        let end = self.len + other.len();
+        if let Some(dst) = self.buffer.get_mut(self.len..end) {
+            dst.copy_from_slice(other);
+        }
+        self.len = end;
+    }
+
+    fn push(&mut self, byte: u8) {
+        if let Some(dst) = self.buffer.get_mut(self.len) {
+            *dst = byte;
+        }
+        self.len += 1;
+    }
+
+    /// The buffer cannot grow, so this is a no-op.
+    fn reserve(&mut self, _additional: usize) {}
+}
+
+/// [`SerOutput`] decorator counting the bytes written to `T`,
+/// e.g. to feed a metrics counter.
+#[derive(Clone, Debug, Default)]
+pub struct MeteredOutput<T> {
+    inner: T,
+    bytes_written: usize,
+}
+
+impl<T> MeteredOutput<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            bytes_written: 0,
+        }
+    }
+
+    /// Total number of bytes written so far.
+    pub fn bytes_written(&self) -> usize {
+        self.bytes_written
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: SerOutput> SerOutput for MeteredOutput<T> {
+    fn extend_from_slice(&mut self, other: &[u8]) {
+        self.inner.extend_from_slice(other);
+        self.bytes_written += other.len();
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.inner.push(byte);
+        self.bytes_written += 1;
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.inner.reserve(additional);
+    }
+}
+
+impl<T: SerOutputBuf> SerOutputBuf for MeteredOutput<T> {
+    fn as_slice(&self) -> &[u8] {
+        self.inner.as_slice()
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self.inner.as_mut_slice()
+    }
+
+    /// Bytes removed are no longer counted by [`MeteredOutput::bytes_written`],
+    /// while bytes already in the inner buffer when it was wrapped were never
+    /// counted.
+    fn truncate(&mut self, len: usize) {
+        let removed = self.inner.as_slice().len().saturating_sub(len);
+        self.inner.truncate(len);
+        self.bytes_written -= removed.min(self.bytes_written);
+    }
+}
+
+/// [`SerOutput`] discarding everything written to it and only counting
+/// the bytes, for computing the serialized size without allocating.
+///
+/// `Serializer<LenCounter>` can be used to compute the header with
+/// [`crate::Serializer::create_header`] before serializing the body, e.g.
+/// into a single preallocated buffer.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct LenCounter {
+    /// Number of bytes written so far.
+    pub len: usize,
+}
+
+impl LenCounter {
+    pub const fn new() -> Self {
+        Self { len: 0 }
+    }
+}
+
+impl SerOutput for LenCounter {
+    fn extend_from_slice(&mut self, other: &[u8]) {
+        self.len += other.len();
+    }
+
+    fn push(&mut self, _byte: u8) {
+        self.len += 1;
+    }
+
+    fn reserve(&mut self, _additional: usize) {}
+}
+
+/// [`SerOutput`] forwarding everything to an [`io::Write`].
+///
+/// Since [`SerOutput`] is infallible, the first error is stored and
+/// everything written after it is discarded.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub(crate) struct WriteOutput<W> {
+    writer: W,
+    len: usize,
+    error: Option<io::Error>,
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> WriteOutput<W> {
+    pub(crate) fn new(writer: W) -> Self {
+        Self {
+            writer,
+            len: 0,
+            error: None,
+        }
+    }
+
+    /// Return the number of bytes written, or the first error encountered.
+    pub(crate) fn finish(self) -> io::Result<usize> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.len),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> SerOutput for WriteOutput<W> {
+    fn extend_from_slice(&mut self, other: &[u8]) {
+        if self.error.is_none() {
+            match self.writer.write_all(other) {
+                Ok(()) => self.len += other.len(),
+                Err(err) => self.error = Some(err),
+            }
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.extend_from_slice(&[byte])
+    }
+
+    /// Nothing is buffered, so this is a no-op.
+    fn reserve(&mut self, _additional: usize) {}
+}
@@ -0,0 +1,141 @@
+//! Encode enum as variant name (length(`u32`) + content) + content
+//! encoded as-is, instead of variant index (`u32`) + content.
+//!
+//! Use it with `#[serde(with = "ssh_format::string_tagged_enum")]` on a
+//! field of enum type.
+
+use alloc::string::String;
+use core::marker::PhantomData;
+
+use serde::{
+    de::{self, DeserializeSeed, IntoDeserializer, SeqAccess},
+    ser::{self, SerializeTuple},
+    Deserialize, Serialize,
+};
+
+use crate::tagged_enum::{Tag, TagDeserializer, TagSerializer};
+
+pub fn serialize<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: ser::Serializer,
+    T: ?Sized + Serialize,
+{
+    value.serialize(TagSerializer(serializer, PhantomData::<StringTag>))
+}
+
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: de::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(TagDeserializer(deserializer, PhantomData::<StringTag>))
+}
+
+struct StringTag;
+
+impl Tag for StringTag {
+    const NOT_ENUM: &'static str = "string_tagged_enum only supports enum";
+    const EXPECTING: &'static str = "variant name followed by its content";
+
+    fn serialize<S>(
+        tuple: &mut S,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), S::Error>
+    where
+        S: SerializeTuple,
+    {
+        tuple.serialize_element(variant)
+    }
+
+    fn deserialize<'de, A, V>(seq: &mut A, seed: V) -> Result<V::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+        V: DeserializeSeed<'de>,
+    {
+        let name: String = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &Self::EXPECTING))?;
+        seed.deserialize(name.into_deserializer())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{from_bytes, to_bytes, Deserializer, Error};
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    enum Request {
+        Ping,
+        Forward(u32),
+        Move(u16, u16),
+        Open { host: String, port: u32 },
+    }
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Message {
+        id: u32,
+        #[serde(with = "crate::string_tagged_enum")]
+        request: Request,
+        trailer: u8,
+    }
+
+    fn test_roundtrip(request: Request, expected_body: &[u8]) {
+        let message = Message {
+            id: 0x12,
+            request,
+            trailer: 0xff,
+        };
+
+        let serialized = to_bytes(&message).unwrap();
+        let body = &serialized[4..];
+
+        assert_eq!(body[..4], [0, 0, 0, 0x12]);
+        assert_eq!(&body[4..body.len() - 1], expected_body);
+        assert_eq!(body[body.len() - 1], 0xff);
+
+        assert_eq!(from_bytes::<Message>(body).unwrap().0, message);
+
+        for chunk_size in 1..body.len() {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+            assert_eq!(Message::deserialize(&mut deserializer).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn test_unit_variant() {
+        test_roundtrip(Request::Ping, b"\0\0\0\x04Ping");
+    }
+
+    #[test]
+    fn test_newtype_variant() {
+        test_roundtrip(Request::Forward(0x1234), b"\0\0\0\x07Forward\0\0\x12\x34");
+    }
+
+    #[test]
+    fn test_tuple_variant() {
+        test_roundtrip(Request::Move(1, 2), b"\0\0\0\x04Move\0\x01\0\x02");
+    }
+
+    #[test]
+    fn test_struct_variant() {
+        test_roundtrip(
+            Request::Open {
+                host: "localhost".to_owned(),
+                port: 22,
+            },
+            b"\0\0\0\x04Open\0\0\0\x09localhost\0\0\0\x16",
+        );
+    }
+
+    #[test]
+    fn test_unknown_variant() {
+        let serialized = to_bytes(&(0x12_u32, "Close", 0xff_u8)).unwrap();
+        assert!(matches!(
+            from_bytes::<Message>(&serialized[4..]),
+            Err(Error::Message(_))
+        ));
+    }
+}
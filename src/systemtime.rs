@@ -0,0 +1,138 @@
+//! `#[serde(with)]` helpers encoding [`SystemTime`] as a single `u64` since
+//! the Unix epoch, instead of serde's `{ secs, nanos }` struct.
+//!
+//! Times before the Unix epoch cannot be serialized.
+
+use std::{
+    convert::TryInto,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{de, ser, Deserialize, Deserializer, Serializer};
+
+fn since_epoch<E: ser::Error>(time: &SystemTime) -> Result<Duration, E> {
+    time.duration_since(UNIX_EPOCH)
+        .map_err(|_| E::custom("SystemTime before UNIX epoch"))
+}
+
+fn from_epoch<E: de::Error>(duration: Duration) -> Result<SystemTime, E> {
+    UNIX_EPOCH
+        .checked_add(duration)
+        .ok_or_else(|| E::custom("SystemTime out of range"))
+}
+
+/// Encode [`SystemTime`] as seconds since the Unix epoch, truncating any
+/// fractional second.
+///
+/// Use it with `#[serde(with = "ssh_format::systemtime_unix_secs")]`.
+pub mod systemtime_unix_secs {
+    use super::*;
+
+    pub fn serialize<S>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u64(since_epoch(time)?.as_secs())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SystemTime, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        from_epoch(Duration::from_secs(u64::deserialize(deserializer)?))
+    }
+}
+
+/// Encode [`SystemTime`] as milliseconds since the Unix epoch, truncating
+/// any fractional millisecond.
+///
+/// Use it with `#[serde(with = "ssh_format::systemtime_unix_millis")]`.
+pub mod systemtime_unix_millis {
+    use super::*;
+
+    pub fn serialize<S>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let millis = since_epoch(time)?
+            .as_millis()
+            .try_into()
+            .map_err(|_| ser::Error::custom("SystemTime out of range"))?;
+        serializer.serialize_u64(millis)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SystemTime, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        from_epoch(Duration::from_millis(u64::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{from_bytes, to_bytes, Error};
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Times {
+        #[serde(with = "crate::systemtime_unix_secs")]
+        secs: SystemTime,
+        #[serde(with = "crate::systemtime_unix_millis")]
+        millis: SystemTime,
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let time = UNIX_EPOCH + Duration::new(0x1234_5678, 987_654_321);
+
+        let times = Times {
+            secs: time,
+            millis: time,
+        };
+        let serialized = to_bytes(&times).unwrap();
+        assert_eq!(serialized[..4], [0, 0, 0, 16]);
+        assert_eq!(serialized[4..12], 0x1234_5678_u64.to_be_bytes());
+        assert_eq!(
+            serialized[12..],
+            (0x1234_5678_u64 * 1000 + 987).to_be_bytes()
+        );
+
+        // Sub-second precision is truncated
+        let (deserialized, _) = from_bytes::<Times>(&serialized[4..]).unwrap();
+        assert_eq!(
+            deserialized.secs,
+            UNIX_EPOCH + Duration::from_secs(0x1234_5678)
+        );
+        assert_eq!(
+            deserialized.millis,
+            UNIX_EPOCH + Duration::from_millis(0x1234_5678 * 1000 + 987)
+        );
+
+        let times = Times {
+            secs: UNIX_EPOCH,
+            millis: UNIX_EPOCH,
+        };
+        let serialized = to_bytes(&times).unwrap();
+        assert_eq!(serialized[4..], [0; 16]);
+        assert_eq!(from_bytes::<Times>(&serialized[4..]).unwrap().0, times);
+    }
+
+    #[test]
+    fn test_before_epoch() {
+        let times = Times {
+            secs: UNIX_EPOCH - Duration::from_secs(1),
+            millis: UNIX_EPOCH,
+        };
+        assert!(matches!(to_bytes(&times), Err(Error::Message(_))));
+
+        let times = Times {
+            secs: UNIX_EPOCH,
+            millis: UNIX_EPOCH - Duration::from_millis(1),
+        };
+        assert!(matches!(to_bytes(&times), Err(Error::Message(_))));
+    }
+}
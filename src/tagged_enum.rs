@@ -0,0 +1,359 @@
+//! Shared implementation of `with` modules encoding enums with a custom
+//! tag instead of the variant index (`u32`).
+
+use core::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{self, DeserializeSeed, EnumAccess, SeqAccess, VariantAccess, Visitor},
+    forward_to_deserialize_any,
+    ser::{self, Impossible, SerializeTuple},
+    Serialize,
+};
+
+/// Encoding of the tag written before the content of a variant.
+pub(crate) trait Tag {
+    /// Error message for values which are not enums.
+    const NOT_ENUM: &'static str;
+
+    /// Description of the expected input used in error messages.
+    const EXPECTING: &'static str;
+
+    fn serialize<S>(
+        tuple: &mut S,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), S::Error>
+    where
+        S: SerializeTuple;
+
+    /// Read the tag and deserialize the variant identifier from it.
+    fn deserialize<'de, A, V>(seq: &mut A, seed: V) -> Result<V::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+        V: DeserializeSeed<'de>;
+}
+
+pub(crate) struct TagSerializer<S, T>(pub(crate) S, pub(crate) PhantomData<T>);
+
+macro_rules! impl_unsupported {
+    ( $( $name:ident($type:ty), )* ) => {
+        $(
+            fn $name(self, _v: $type) -> Result<S::Ok, S::Error> {
+                Err(ser::Error::custom(T::NOT_ENUM))
+            }
+        )*
+    };
+}
+
+impl<S: ser::Serializer, T: Tag> ser::Serializer for TagSerializer<S, T> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    type SerializeSeq = Impossible<S::Ok, S::Error>;
+    type SerializeTuple = Impossible<S::Ok, S::Error>;
+    type SerializeTupleStruct = Impossible<S::Ok, S::Error>;
+    type SerializeTupleVariant = Variant<S::SerializeTuple>;
+    type SerializeMap = Impossible<S::Ok, S::Error>;
+    type SerializeStruct = Impossible<S::Ok, S::Error>;
+    type SerializeStructVariant = Variant<S::SerializeTuple>;
+
+    impl_unsupported!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_unit_struct(&'static str),
+    );
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        Err(ser::Error::custom(T::NOT_ENUM))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, S::Error> {
+        Err(ser::Error::custom(T::NOT_ENUM))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        Err(ser::Error::custom(T::NOT_ENUM))
+    }
+
+    fn serialize_none(self) -> Result<S::Ok, S::Error> {
+        Err(ser::Error::custom(T::NOT_ENUM))
+    }
+
+    fn serialize_some<V>(self, _value: &V) -> Result<S::Ok, S::Error>
+    where
+        V: ?Sized + Serialize,
+    {
+        Err(ser::Error::custom(T::NOT_ENUM))
+    }
+
+    fn serialize_unit(self) -> Result<S::Ok, S::Error> {
+        Err(ser::Error::custom(T::NOT_ENUM))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, S::Error> {
+        Err(ser::Error::custom(T::NOT_ENUM))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, S::Error> {
+        Err(ser::Error::custom(T::NOT_ENUM))
+    }
+
+    fn serialize_newtype_struct<V>(self, _name: &'static str, value: &V) -> Result<S::Ok, S::Error>
+    where
+        V: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<S::Ok, S::Error> {
+        let mut tuple = self.0.serialize_tuple(1)?;
+        T::serialize(&mut tuple, variant_index, variant)?;
+        tuple.end()
+    }
+
+    fn serialize_newtype_variant<V>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &V,
+    ) -> Result<S::Ok, S::Error>
+    where
+        V: ?Sized + Serialize,
+    {
+        let mut tuple = self.0.serialize_tuple(2)?;
+        T::serialize(&mut tuple, variant_index, variant)?;
+        tuple.serialize_element(value)?;
+        tuple.end()
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, S::Error> {
+        let mut tuple = self.0.serialize_tuple(1 + len)?;
+        T::serialize(&mut tuple, variant_index, variant)?;
+        Ok(Variant(tuple))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, S::Error> {
+        let mut tuple = self.0.serialize_tuple(1 + len)?;
+        T::serialize(&mut tuple, variant_index, variant)?;
+        Ok(Variant(tuple))
+    }
+
+    #[cfg(feature = "is_human_readable")]
+    fn is_human_readable(&self) -> bool {
+        self.0.is_human_readable()
+    }
+}
+
+/// Fields of tuple/struct variant, serialized after the tag.
+pub(crate) struct Variant<T>(T);
+
+impl<T: SerializeTuple> ser::SerializeTupleVariant for Variant<T> {
+    type Ok = T::Ok;
+    type Error = T::Error;
+
+    fn serialize_field<V>(&mut self, value: &V) -> Result<(), T::Error>
+    where
+        V: ?Sized + Serialize,
+    {
+        self.0.serialize_element(value)
+    }
+
+    fn end(self) -> Result<T::Ok, T::Error> {
+        self.0.end()
+    }
+}
+
+impl<T: SerializeTuple> ser::SerializeStructVariant for Variant<T> {
+    type Ok = T::Ok;
+    type Error = T::Error;
+
+    fn serialize_field<V>(&mut self, _key: &'static str, value: &V) -> Result<(), T::Error>
+    where
+        V: ?Sized + Serialize,
+    {
+        self.0.serialize_element(value)
+    }
+
+    fn end(self) -> Result<T::Ok, T::Error> {
+        self.0.end()
+    }
+}
+
+pub(crate) struct TagDeserializer<D, T>(pub(crate) D, pub(crate) PhantomData<T>);
+
+impl<'de, D: de::Deserializer<'de>, T: Tag> de::Deserializer<'de> for TagDeserializer<D, T> {
+    type Error = D::Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, D::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::custom(T::NOT_ENUM))
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, D::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, D::Error>
+    where
+        V: Visitor<'de>,
+    {
+        // The tag and the content
+        self.0
+            .deserialize_tuple(2, EnumVisitor(visitor, PhantomData::<T>))
+    }
+
+    #[cfg(feature = "is_human_readable")]
+    fn is_human_readable(&self) -> bool {
+        self.0.is_human_readable()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct EnumVisitor<V, T>(V, PhantomData<T>);
+
+impl<'de, V: Visitor<'de>, T: Tag> Visitor<'de> for EnumVisitor<V, T> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.0.expecting(formatter)
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<V::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        self.0.visit_enum(Enum(seq, self.1))
+    }
+}
+
+struct Enum<A, T>(A, PhantomData<T>);
+
+impl<A, T: Tag> Enum<A, T> {
+    fn missing<E: de::Error>() -> E {
+        de::Error::invalid_length(0, &T::EXPECTING)
+    }
+}
+
+impl<'de, A: SeqAccess<'de>, T: Tag> EnumAccess<'de> for Enum<A, T> {
+    type Error = A::Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(mut self, seed: V) -> Result<(V::Value, Self), A::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let value = T::deserialize(&mut self.0, seed)?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, A: SeqAccess<'de>, T: Tag> VariantAccess<'de> for Enum<A, T> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), A::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S>(mut self, seed: S) -> Result<S::Value, A::Error>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        self.0.next_element_seed(seed)?.ok_or_else(Self::missing)
+    }
+
+    fn tuple_variant<V>(mut self, len: usize, visitor: V) -> Result<V::Value, A::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.0
+            .next_element_seed(TupleSeed { len, visitor })?
+            .ok_or_else(Self::missing)
+    }
+
+    fn struct_variant<V>(
+        mut self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, A::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let len = fields.len();
+        self.0
+            .next_element_seed(TupleSeed { len, visitor })?
+            .ok_or_else(Self::missing)
+    }
+}
+
+struct TupleSeed<V> {
+    len: usize,
+    visitor: V,
+}
+
+impl<'de, V: Visitor<'de>> DeserializeSeed<'de> for TupleSeed<V> {
+    type Value = V::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<V::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(self.len, self.visitor)
+    }
+}
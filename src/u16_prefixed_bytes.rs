@@ -0,0 +1,129 @@
+//! Encode bytes as length(`u16`) + content, for compact sub-records which
+//! use a `u16` length prefix instead of the usual `u32`.
+//!
+//! Serializing more than `u16::MAX` bytes is an error.
+//!
+//! Use it with `#[serde(with = "ssh_format::u16_prefixed_bytes")]` on any
+//! type implementing `AsRef<[u8]>` and `From<Vec<u8>>`, e.g. `Vec<u8>`.
+//! See [`crate::u16_prefixed_str`] for strings.
+
+use alloc::vec::Vec;
+use core::{convert::TryFrom, fmt};
+
+use serde::{
+    de::{self, SeqAccess},
+    ser::{self, SerializeTuple},
+    Deserializer, Serializer,
+};
+
+pub fn serialize<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: AsRef<[u8]> + ?Sized,
+{
+    let bytes = value.as_ref();
+    let len = u16::try_from(bytes.len()).map_err(|_| {
+        ser::Error::custom(format_args!(
+            "{} bytes do not fit in a u16 length",
+            bytes.len()
+        ))
+    })?;
+
+    let mut tuple = serializer.serialize_tuple(1 + bytes.len())?;
+    tuple.serialize_element(&len)?;
+    for byte in bytes {
+        tuple.serialize_element(byte)?;
+    }
+    tuple.end()
+}
+
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: From<Vec<u8>>,
+{
+    struct U16PrefixedVisitor;
+
+    impl<'de> de::Visitor<'de> for U16PrefixedVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("bytes prefixed with a u16 length")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Vec<u8>, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let len: u16 = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+
+            let mut bytes = Vec::with_capacity(len.into());
+            for i in 0..len {
+                let byte: u8 = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1 + usize::from(i), &self))?;
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+    }
+
+    deserializer
+        .deserialize_tuple(usize::MAX, U16PrefixedVisitor)
+        .map(T::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{from_bytes, to_bytes, Deserializer, Error};
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        #[serde(with = "crate::u16_prefixed_bytes")]
+        data: Vec<u8>,
+        flags: u8,
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let record = Record {
+            data: vec![1, 2, 3],
+            flags: 0xff,
+        };
+
+        let serialized = to_bytes(&record).unwrap();
+        let body = &serialized[4..];
+        assert_eq!(body, [0, 3, 1, 2, 3, 0xff]);
+
+        assert_eq!(from_bytes::<Record>(body).unwrap().0, record);
+
+        for chunk_size in 1..body.len() {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+            assert_eq!(Record::deserialize(&mut deserializer).unwrap(), record);
+        }
+
+        // Truncated content
+        assert!(matches!(from_bytes::<Record>(&body[..4]), Err(Error::Eof)));
+    }
+
+    #[test]
+    fn test_too_long() {
+        let record = Record {
+            data: vec![0; usize::from(u16::MAX) + 1],
+            flags: 0,
+        };
+        assert!(matches!(to_bytes(&record), Err(Error::Message(_))));
+
+        let record = Record {
+            data: vec![0; u16::MAX.into()],
+            flags: 0,
+        };
+        let serialized = to_bytes(&record).unwrap();
+        assert_eq!(serialized[4..6], [0xff, 0xff]);
+        assert_eq!(from_bytes::<Record>(&serialized[4..]).unwrap().0, record);
+    }
+}
@@ -0,0 +1,73 @@
+//! Encode a string as length(`u16`) + content, the string counterpart of
+//! [`crate::u16_prefixed_bytes`].
+//!
+//! Serializing more than `u16::MAX` bytes is an error.
+//!
+//! Use it with `#[serde(with = "ssh_format::u16_prefixed_str")]` on any
+//! type implementing `AsRef<str>` and `From<String>`, e.g. `String`.
+
+use alloc::{string::String, vec::Vec};
+
+use serde::{de, Deserializer, Serializer};
+
+use crate::u16_prefixed_bytes;
+
+pub fn serialize<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: AsRef<str> + ?Sized,
+{
+    u16_prefixed_bytes::serialize(value.as_ref().as_bytes(), serializer)
+}
+
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: From<String>,
+{
+    let bytes: Vec<u8> = u16_prefixed_bytes::deserialize(deserializer)?;
+    String::from_utf8(bytes)
+        .map(T::from)
+        .map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{from_bytes, to_bytes, Error};
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        #[serde(with = "crate::u16_prefixed_str")]
+        name: String,
+        id: u32,
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let record = Record {
+            name: "eth0".to_owned(),
+            id: 1,
+        };
+
+        let serialized = to_bytes(&record).unwrap();
+        let body = &serialized[4..];
+        assert_eq!(body, b"\x00\x04eth0\x00\x00\x00\x01");
+        assert_eq!(from_bytes::<Record>(body).unwrap().0, record);
+    }
+
+    #[test]
+    fn test_errors() {
+        let record = Record {
+            name: "x".repeat(usize::from(u16::MAX) + 1),
+            id: 1,
+        };
+        assert!(matches!(to_bytes(&record), Err(Error::Message(_))));
+
+        assert!(matches!(
+            from_bytes::<Record>(b"\x00\x01\xff\x00\x00\x00\x01"),
+            Err(Error::Message(_))
+        ));
+    }
+}
@@ -0,0 +1,192 @@
+//! Encode enum as variant index (`u8`) + content encoded as-is, instead
+//! of variant index (`u32`) + content, for compact sub-protocols using a
+//! single byte as the variant tag.
+//!
+//! Serializing a variant whose index does not fit in `u8` is an error.
+//!
+//! Use it with `#[serde(with = "ssh_format::u8_variant")]` on a field of
+//! enum type, or wrap the enum in [`U8Variant`] where no field attribute
+//! can be used, e.g. `Vec<U8Variant<T>>` or a top-level value.
+
+use core::{convert::TryFrom, marker::PhantomData};
+
+use serde::{
+    de::{self, DeserializeSeed, IntoDeserializer, SeqAccess},
+    ser::{self, SerializeTuple},
+    Deserialize, Serialize,
+};
+
+use crate::tagged_enum::{Tag, TagDeserializer, TagSerializer};
+
+pub fn serialize<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: ser::Serializer,
+    T: ?Sized + Serialize,
+{
+    value.serialize(TagSerializer(serializer, PhantomData::<U8Tag>))
+}
+
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: de::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(TagDeserializer(deserializer, PhantomData::<U8Tag>))
+}
+
+/// Wrapper encoding the enum `T` with a `u8` variant index, same as
+/// `#[serde(with = "ssh_format::u8_variant")]`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct U8Variant<T>(pub T);
+
+impl<T: Serialize> Serialize for U8Variant<T> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize(&self.0, serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for U8Variant<T> {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize(deserializer).map(U8Variant)
+    }
+}
+
+struct U8Tag;
+
+impl Tag for U8Tag {
+    const NOT_ENUM: &'static str = "u8_variant only supports enum";
+    const EXPECTING: &'static str = "variant index (u8) followed by its content";
+
+    fn serialize<S>(
+        tuple: &mut S,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), S::Error>
+    where
+        S: SerializeTuple,
+    {
+        let index = u8::try_from(variant_index).map_err(|_| {
+            ser::Error::custom(format_args!(
+                "index {} of variant {} does not fit in u8",
+                variant_index, variant
+            ))
+        })?;
+        tuple.serialize_element(&index)
+    }
+
+    fn deserialize<'de, A, V>(seq: &mut A, seed: V) -> Result<V::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+        V: DeserializeSeed<'de>,
+    {
+        let index: u8 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &Self::EXPECTING))?;
+        seed.deserialize(u32::from(index).into_deserializer())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{from_bytes, to_bytes, Deserializer, Error};
+
+    #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+    enum Request {
+        Ping,
+        Forward(u32),
+        Move(u16, u16),
+        Open { host: String, port: u32 },
+    }
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Message {
+        id: u32,
+        #[serde(with = "crate::u8_variant")]
+        request: Request,
+        trailer: u8,
+    }
+
+    fn test_roundtrip(request: Request, expected_body: &[u8]) {
+        let message = Message {
+            id: 0x12,
+            request: request.clone(),
+            trailer: 0xff,
+        };
+
+        let serialized = to_bytes(&message).unwrap();
+        let body = &serialized[4..];
+
+        assert_eq!(body[..4], [0, 0, 0, 0x12]);
+        assert_eq!(&body[4..body.len() - 1], expected_body);
+        assert_eq!(body[body.len() - 1], 0xff);
+
+        assert_eq!(from_bytes::<Message>(body).unwrap().0, message);
+
+        for chunk_size in 1..body.len() {
+            let mut deserializer = Deserializer::new(body.chunks(chunk_size).fuse());
+            assert_eq!(Message::deserialize(&mut deserializer).unwrap(), message);
+        }
+
+        // The default encoding still uses a u32 index
+        let serialized = to_bytes(&request).unwrap();
+        assert_eq!(serialized[4..8], [0, 0, 0, expected_body[0]]);
+        assert_eq!(serialized[8..], expected_body[1..]);
+    }
+
+    #[test]
+    fn test_variants() {
+        test_roundtrip(Request::Ping, b"\x00");
+        test_roundtrip(Request::Forward(0x1234), b"\x01\0\0\x12\x34");
+        test_roundtrip(Request::Move(1, 2), b"\x02\0\x01\0\x02");
+        test_roundtrip(
+            Request::Open {
+                host: "localhost".to_owned(),
+                port: 22,
+            },
+            b"\x03\0\0\0\x09localhost\0\0\0\x16",
+        );
+    }
+
+    #[test]
+    fn test_wrapper() {
+        use super::U8Variant;
+
+        let requests = vec![U8Variant(Request::Ping), U8Variant(Request::Move(1, 2))];
+
+        let serialized = to_bytes(&requests).unwrap();
+        let body = &serialized[4..];
+        assert_eq!(body, b"\0\0\0\x02\x00\x02\0\x01\0\x02");
+
+        assert_eq!(
+            from_bytes::<Vec<U8Variant<Request>>>(body).unwrap().0,
+            requests
+        );
+    }
+
+    #[test]
+    fn test_unknown_variant() {
+        let serialized = to_bytes(&(0x12_u32, 4_u8, 0xff_u8)).unwrap();
+        assert!(matches!(
+            from_bytes::<Message>(&serialized[4..]),
+            Err(Error::Message(_))
+        ));
+    }
+
+    #[test]
+    fn test_index_too_large() {
+        struct Large;
+
+        impl Serialize for Large {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_unit_variant("Large", 256, "Large")
+            }
+        }
+
+        #[derive(Serialize)]
+        struct Wrapper(#[serde(with = "crate::u8_variant")] Large);
+
+        assert!(matches!(to_bytes(&Wrapper(Large)), Err(Error::Message(_))));
+    }
+}
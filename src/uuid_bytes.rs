@@ -0,0 +1,61 @@
+//! Serialize and deserialize [`Uuid`] as its 16 raw bytes without length,
+//! like a C `u_char[16]`.
+//!
+//! Use it with `#[serde(with = "ssh_format::uuid_bytes")]`.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+
+pub fn serialize<S>(uuid: &Uuid, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    uuid.as_bytes().serialize(serializer)
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Uuid, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    <[u8; 16]>::deserialize(deserializer).map(Uuid::from_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use uuid::Uuid;
+
+    use crate::{from_bytes, to_bytes, Deserializer};
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct Session {
+        #[serde(with = "crate::uuid_bytes")]
+        id: Uuid,
+        flags: u32,
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let session = Session {
+            id: Uuid::from_u128(0x0011_2233_4455_6677_8899_aabb_ccdd_eeff),
+            flags: 1,
+        };
+
+        let serialized = to_bytes(&session).unwrap();
+        assert_eq!(serialized[..4], [0, 0, 0, 20]);
+        assert_eq!(
+            serialized[4..20],
+            [
+                0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd,
+                0xee, 0xff
+            ]
+        );
+        assert_eq!(serialized[20..], [0, 0, 0, 1]);
+
+        let body = &serialized[4..];
+        assert_eq!(from_bytes::<Session>(body).unwrap().0, session);
+
+        let mut deserializer = Deserializer::new(body.chunks(3).fuse());
+        assert_eq!(Session::deserialize(&mut deserializer).unwrap(), session);
+    }
+}
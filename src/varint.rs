@@ -0,0 +1,160 @@
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{from_bytes, Error, Result, Serializer};
+
+/// Maximum length of a LEB128-encoded `u32`.
+const MAX_VARINT_LEN: usize = 5;
+
+fn encode_varint(mut v: u32, buffer: &mut Vec<u8>) {
+    while v >= 0x80 {
+        buffer.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buffer.push(v as u8);
+}
+
+/// Return the decoded value and the bytes following it.
+fn decode_varint(s: &[u8]) -> Result<(u32, &[u8])> {
+    let mut v: u32 = 0;
+
+    for (i, byte) in s.iter().take(MAX_VARINT_LEN).enumerate() {
+        let bits = u32::from(byte & 0x7f);
+        let shift = 7 * i as u32;
+
+        if bits.checked_shl(shift).map(|shifted| shifted >> shift) != Some(bits) {
+            return Err(Error::TooLong);
+        }
+        v |= bits << shift;
+
+        if byte & 0x80 == 0 {
+            return Ok((v, &s[i + 1..]));
+        }
+    }
+
+    if s.len() < MAX_VARINT_LEN {
+        Err(Error::Eof)
+    } else {
+        Err(Error::TooLong)
+    }
+}
+
+/// Return a byte array of `[len][body]`, where `len` is the size of the
+/// body encoded as a LEB128 varint instead of a `u32`.
+///
+/// The body is encoded exactly as in [`crate::to_bytes`].
+pub fn to_bytes_varint<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize,
+{
+    let mut serializer: Serializer = Serializer::default();
+    value.serialize(&mut serializer)?;
+    let len = u32::from_be_bytes(serializer.create_header(0)?);
+
+    let mut buffer = Vec::with_capacity(MAX_VARINT_LEN + serializer.output.len());
+    encode_varint(len, &mut buffer);
+    buffer.extend_from_slice(&serializer.output);
+
+    Ok(buffer)
+}
+
+/// Return a deserialized value and the bytes following its frame.
+///
+/// `s` must start with a frame written by [`to_bytes_varint`].
+pub fn from_bytes_varint<'a, T>(s: &'a [u8]) -> Result<(T, &'a [u8])>
+where
+    T: Deserialize<'a>,
+{
+    let (len, s) = decode_varint(s)?;
+    let len: usize = len.try_into().map_err(|_| Error::TooLong)?;
+
+    if s.len() < len {
+        return Err(Error::Eof);
+    }
+    let (frame, rest) = s.split_at(len);
+
+    let (t, _trailing_bytes) = from_bytes(frame)?;
+    Ok((t, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+    use crate::to_bytes;
+
+    #[test]
+    fn test_varint() {
+        for (v, encoded) in [
+            (0, &[0x00][..]),
+            (0x7f, &[0x7f]),
+            (0x80, &[0x80, 0x01]),
+            (300, &[0xac, 0x02]),
+            (0x3fff, &[0xff, 0x7f]),
+            (0x4000, &[0x80, 0x80, 0x01]),
+            (u32::MAX, &[0xff, 0xff, 0xff, 0xff, 0x0f]),
+        ] {
+            let mut buffer = Vec::new();
+            encode_varint(v, &mut buffer);
+            assert_eq!(buffer, encoded);
+
+            buffer.push(0xaa);
+            assert_eq!(decode_varint(&buffer).unwrap(), (v, &[0xaa][..]));
+        }
+
+        assert_matches!(decode_varint(&[]), Err(Error::Eof));
+        assert_matches!(decode_varint(&[0x80, 0x80]), Err(Error::Eof));
+        assert_matches!(
+            decode_varint(&[0xff, 0xff, 0xff, 0xff, 0x1f]),
+            Err(Error::TooLong)
+        );
+        assert_matches!(
+            decode_varint(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00]),
+            Err(Error::TooLong)
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let serialized = to_bytes_varint(&()).unwrap();
+        assert_eq!(serialized, [0]);
+        assert_eq!(from_bytes_varint::<()>(&serialized).unwrap(), ((), &[][..]));
+
+        // Body is the `u32` count followed by the bytes, with 1-, 2- and
+        // 3-byte varint prefixes.
+        for (n, prefix) in [
+            (0x7b, &[0x7f][..]),
+            (0x7c, &[0x80, 0x01]),
+            (0x3ffb, &[0xff, 0x7f]),
+            (0x3ffc, &[0x80, 0x80, 0x01]),
+        ] {
+            let value: Vec<u8> = (0..n).map(|i| i as u8).collect();
+            let plain = to_bytes(&value).unwrap();
+
+            let mut serialized = to_bytes_varint(&value).unwrap();
+            assert_eq!(serialized[..prefix.len()], *prefix);
+            assert_eq!(serialized[prefix.len()..], plain[4..]);
+
+            serialized.extend_from_slice(&to_bytes_varint(&0x12345678_u32).unwrap());
+
+            let (deserialized, rest) = from_bytes_varint::<Vec<u8>>(&serialized).unwrap();
+            assert_eq!(deserialized, value);
+
+            let (deserialized, rest) = from_bytes_varint::<u32>(rest).unwrap();
+            assert_eq!(deserialized, 0x12345678);
+            assert!(rest.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_truncated() {
+        let serialized = to_bytes_varint(&"Hello").unwrap();
+        assert_matches!(
+            from_bytes_varint::<&str>(&serialized[..serialized.len() - 1]),
+            Err(Error::Eof)
+        );
+    }
+}
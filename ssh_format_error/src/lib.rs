@@ -1,14 +1,21 @@
-use std::{
-    error,
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::{
+    boxed::Box,
+    string::{FromUtf8Error, ToString},
+};
+use core::{
     fmt::{self, Display},
-    io,
     str::Utf8Error,
-    string::FromUtf8Error,
 };
+#[cfg(feature = "std")]
+use std::io;
 
 use serde::{de, ser};
 
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
 
 #[derive(Debug)]
 #[non_exhaustive]
@@ -16,7 +23,10 @@ pub enum Error {
     Message(Box<str>),
     Eof,
     InvalidBoolEncoding,
-    InvalidChar,
+
+    /// The `u32` is not a valid `char`, e.g. a surrogate or a value
+    /// larger than `char::MAX`.
+    InvalidChar(u32),
     InvalidStr(Utf8Error),
 
     /// Unsupported error.
@@ -25,6 +35,45 @@ pub enum Error {
     Unsupported(&'static &'static str),
     TooLong,
 
+    /// The output buffer is too small, `needed` bytes are required.
+    BufferTooSmall {
+        needed: usize,
+    },
+
+    /// The checksum of the message does not match the one computed.
+    ChecksumMismatch {
+        expected: u32,
+        computed: u32,
+    },
+
+    /// The opcode of the message does not match the one expected.
+    UnexpectedOpcode {
+        expected: u32,
+        found: u32,
+    },
+
+    /// The length declared in the message does not match the number of
+    /// bytes actually present.
+    InvalidLength {
+        expected: usize,
+        found: usize,
+    },
+
+    /// The length of a string or bytes field exceeds the limit set on
+    /// the deserializer.
+    LengthLimitExceeded {
+        len: usize,
+        max_len: usize,
+    },
+
+    /// The field count trailer of the message does not match the number
+    /// of fields deserialized.
+    FieldCountMismatch {
+        expected: u32,
+        found: u32,
+    },
+
+    #[cfg(feature = "std")]
     IoError(io::Error),
 }
 
@@ -46,27 +95,127 @@ impl Display for Error {
             Error::Message(msg) => f.write_str(msg),
             Error::Eof => f.write_str("EOF"),
             Error::InvalidBoolEncoding => f.write_str("InvalidBoolEncoding"),
-            Error::InvalidChar => f.write_str("Invalid char"),
+            Error::InvalidChar(v) => write!(f, "Invalid char {:#x}", v),
             Error::InvalidStr(err) => write!(f, "Invalid str: {:#?}", err),
             Error::Unsupported(s) => write!(f, "Unsupported {}", s),
             Error::TooLong => f.write_str("Bytes must not be larger than u32::MAX"),
+            Error::BufferTooSmall { needed } => {
+                write!(f, "Buffer too small, {} bytes are needed", needed)
+            }
+            Error::ChecksumMismatch { expected, computed } => write!(
+                f,
+                "Checksum mismatch, expected {:#010x}, computed {:#010x}",
+                expected, computed
+            ),
+            Error::UnexpectedOpcode { expected, found } => {
+                write!(f, "Unexpected opcode {}, expected {}", found, expected)
+            }
+            Error::InvalidLength { expected, found } => write!(
+                f,
+                "Invalid length, {} bytes declared but {} bytes found",
+                expected, found
+            ),
+            Error::LengthLimitExceeded { len, max_len } => {
+                write!(f, "Length {} exceeds the limit of {} bytes", len, max_len)
+            }
+            Error::FieldCountMismatch { expected, found } => write!(
+                f,
+                "Field count mismatch, {} fields declared but {} fields found",
+                expected, found
+            ),
+            #[cfg(feature = "std")]
             Error::IoError(io_error) => write!(f, "Io error: {}", io_error),
         }
     }
 }
 
-impl error::Error for Error {
-    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+/// Two [`Error::IoError`] are equal if their [`io::ErrorKind`] are equal,
+/// whatever their messages.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        use Error::*;
+
+        match (self, other) {
+            (Message(x), Message(y)) => x == y,
+            (Eof, Eof) => true,
+            (InvalidBoolEncoding, InvalidBoolEncoding) => true,
+            (InvalidChar(x), InvalidChar(y)) => x == y,
+            (InvalidStr(x), InvalidStr(y)) => x == y,
+            (Unsupported(x), Unsupported(y)) => x == y,
+            (TooLong, TooLong) => true,
+            (BufferTooSmall { needed: x }, BufferTooSmall { needed: y }) => x == y,
+            (
+                ChecksumMismatch {
+                    expected: x0,
+                    computed: x1,
+                },
+                ChecksumMismatch {
+                    expected: y0,
+                    computed: y1,
+                },
+            ) => (x0, x1) == (y0, y1),
+            (
+                UnexpectedOpcode {
+                    expected: x0,
+                    found: x1,
+                },
+                UnexpectedOpcode {
+                    expected: y0,
+                    found: y1,
+                },
+            ) => (x0, x1) == (y0, y1),
+            (
+                InvalidLength {
+                    expected: x0,
+                    found: x1,
+                },
+                InvalidLength {
+                    expected: y0,
+                    found: y1,
+                },
+            ) => (x0, x1) == (y0, y1),
+            (
+                LengthLimitExceeded {
+                    len: x0,
+                    max_len: x1,
+                },
+                LengthLimitExceeded {
+                    len: y0,
+                    max_len: y1,
+                },
+            ) => (x0, x1) == (y0, y1),
+            (
+                FieldCountMismatch {
+                    expected: x0,
+                    found: x1,
+                },
+                FieldCountMismatch {
+                    expected: y0,
+                    found: y1,
+                },
+            ) => (x0, x1) == (y0, y1),
+            #[cfg(feature = "std")]
+            (IoError(x), IoError(y)) => x.kind() == y.kind(),
+            _ => false,
+        }
+    }
+}
+
+/// This is [`std::error::Error`] if feature `std` is enabled.
+impl de::StdError for Error {
+    fn source(&self) -> Option<&(dyn de::StdError + 'static)> {
         use Error::*;
 
         match self {
             InvalidStr(utf8_err) => Some(utf8_err),
+            #[cfg(feature = "std")]
             IoError(io_error) => Some(io_error),
             _ => None,
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl From<io::Error> for Error {
     fn from(io_error: io::Error) -> Self {
         match io_error.kind() {
@@ -76,6 +225,20 @@ impl From<io::Error> for Error {
     }
 }
 
+/// Convert into [`io::Error`], with the original error preserved as
+/// its inner error (accessible via [`io::Error::get_ref`]) unless it
+/// is already an [`Error::IoError`].
+#[cfg(feature = "std")]
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::IoError(io_error) => io_error,
+            Error::Eof => io::Error::new(io::ErrorKind::UnexpectedEof, err),
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
 impl From<Utf8Error> for Error {
     fn from(utf8_err: Utf8Error) -> Self {
         Error::InvalidStr(utf8_err)
@@ -87,3 +250,83 @@ impl From<FromUtf8Error> for Error {
         from_utf8_err.utf8_error().into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_from_io_error() {
+        let err: Error = io::Error::from(io::ErrorKind::UnexpectedEof).into();
+        assert!(matches!(err, Error::Eof));
+
+        let err: Error = io::Error::from(io::ErrorKind::BrokenPipe).into();
+        assert!(
+            matches!(err, Error::IoError(io_error) if io_error.kind() == io::ErrorKind::BrokenPipe)
+        );
+    }
+
+    #[test]
+    fn test_partial_eq() {
+        assert_eq!(Error::Eof, Error::Eof);
+        assert_eq!(Error::InvalidChar(0xD800), Error::InvalidChar(0xD800));
+        assert_ne!(Error::InvalidChar(0xD800), Error::InvalidChar(0xDFFF));
+        assert_ne!(Error::Eof, Error::TooLong);
+        assert_eq!(
+            Error::InvalidLength {
+                expected: 1,
+                found: 2
+            },
+            Error::InvalidLength {
+                expected: 1,
+                found: 2
+            }
+        );
+        assert_ne!(
+            Error::InvalidLength {
+                expected: 1,
+                found: 2
+            },
+            Error::InvalidLength {
+                expected: 2,
+                found: 1
+            }
+        );
+        assert_eq!(
+            <Error as de::Error>::custom("invalid"),
+            Error::Message("invalid".into())
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_partial_eq_io_error() {
+        // Only the kind of io errors is compared
+        assert_eq!(
+            Error::IoError(io::Error::new(io::ErrorKind::BrokenPipe, "a")),
+            Error::IoError(io::Error::new(io::ErrorKind::BrokenPipe, "b"))
+        );
+        assert_ne!(
+            Error::IoError(io::ErrorKind::BrokenPipe.into()),
+            Error::IoError(io::ErrorKind::ConnectionReset.into())
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_into_io_error() {
+        let io_error: io::Error = Error::InvalidBoolEncoding.into();
+        assert_eq!(io_error.kind(), io::ErrorKind::InvalidData);
+
+        let inner = io_error.get_ref().unwrap().downcast_ref::<Error>();
+        assert!(matches!(inner, Some(Error::InvalidBoolEncoding)));
+
+        let io_error: io::Error = Error::Eof.into();
+        assert_eq!(io_error.kind(), io::ErrorKind::UnexpectedEof);
+
+        let io_error: io::Error = Error::IoError(io::ErrorKind::BrokenPipe.into()).into();
+        assert_eq!(io_error.kind(), io::ErrorKind::BrokenPipe);
+        assert!(io_error.get_ref().is_none());
+    }
+}